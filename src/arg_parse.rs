@@ -21,20 +21,30 @@ use clap::{
     Arg, ArgAction, ArgGroup, Command, ValueEnum, builder::EnumValueParser,
     crate_version, value_parser,
 };
+use regex::Regex;
 use simplelog::LevelFilter;
-use std::{ffi::OsString, fmt, path::PathBuf, process::exit};
+use std::{
+    ffi::OsString,
+    fmt,
+    path::{Path, PathBuf},
+    process::exit,
+};
 use url::Url;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct GlobalOpts {
     pub hostnames: Vec<Url>,
+    pub verify_report: Option<PathBuf>,
     pub wordlist_files: Option<Vec<String>>,
     pub prefixes: Vec<String>,
     pub extensions: Vec<String>,
     pub extension_substitution: bool,
+    pub path_mode: PathMode,
+    pub add_slash: bool,
     pub max_threads: u32,
     pub proxy_enabled: bool,
     pub proxy_address: String,
+    pub unix_socket: Option<PathBuf>,
     #[allow(dead_code, reason = "TODO")]
     pub proxy_auth_enabled: bool,
     pub ignore_cert: bool,
@@ -42,27 +52,53 @@ pub struct GlobalOpts {
     pub throttle: u32,
     pub max_recursion_depth: Option<i32>,
     pub user_agent: Option<String>,
+    pub user_agents: Option<Vec<String>>,
+    pub random_headers: Option<Vec<RandomHeader>>,
     pub username: Option<String>,
     pub password: Option<String>,
     pub output_file: Option<String>,
     pub json_file: Option<String>,
     pub xml_file: Option<String>,
+    pub report_baselines: bool,
+    pub har_file: Option<String>,
+    pub har_body_size: Option<usize>,
     pub timeout: u32,
+    pub max_body_size: Option<usize>,
     pub max_errors: u32,
     pub wordlist_split: u32,
     pub scan_listable: bool,
     pub cookies: Option<String>,
     pub headers: Option<Vec<String>>,
+    pub resolve: Option<Vec<String>>,
+    pub connect_to: Option<Vec<String>>,
     pub scrape_listable: bool,
+    pub spider: bool,
+    pub js_endpoints: bool,
+    pub spider_depth: u32,
+    pub robots_sitemap: bool,
     pub whitelist: bool,
     pub code_list: Vec<u32>,
     pub is_terminal: bool,
     pub no_color: bool,
     pub disable_validator: bool,
+    pub not_found_rules: Vec<NotFoundRule>,
+    pub not_found_only: bool,
+    pub revalidate: Option<u64>,
+    pub validator_probes: u32,
+    pub validator_threads: u32,
+    pub wildcard_threshold: Option<u8>,
+    pub wildcard_requests: u64,
+    pub size_tolerance: usize,
+    pub detect_case: bool,
     pub http_verb: HttpVerb,
     pub scan_opts: ScanOpts,
+    pub bypass_403: bool,
+    pub follow_https_upgrade: bool,
     pub log_level: LevelFilter,
     pub length_blacklist: LengthRanges,
+    pub min_time: Option<u64>,
+    pub max_time: Option<u64>,
+    pub similarity: Option<u8>,
 }
 
 #[derive(Copy, Clone, PartialOrd, Ord, PartialEq, Eq)]
@@ -121,6 +157,38 @@ impl fmt::Display for LengthRanges {
     }
 }
 
+// A header whose value is picked at random from a list for each request
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RandomHeader {
+    pub name: String,
+    pub values: Vec<String>,
+}
+
+// A user supplied description of a not found response, which applies to
+// the paths on the given host and under the given path if they are set
+#[derive(Clone, Debug)]
+pub struct NotFoundRule {
+    pub host: Option<String>,
+    pub path: Option<String>,
+    pub code: Option<u32>,
+    pub size: Option<usize>,
+    pub regex: Option<Regex>,
+}
+
+// Regexes can't be compared directly so compare their patterns instead
+impl PartialEq for NotFoundRule {
+    fn eq(&self, other: &Self) -> bool {
+        self.host == other.host
+            && self.path == other.path
+            && self.code == other.code
+            && self.size == other.size
+            && self.regex.as_ref().map(Regex::as_str)
+                == other.regex.as_ref().map(Regex::as_str)
+    }
+}
+
+impl Eq for NotFoundRule {}
+
 #[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
 pub struct ScanOpts {
     pub scan_401: bool,
@@ -141,6 +209,20 @@ impl Default for HttpVerb {
     }
 }
 
+/// How words are placed into the path of a request
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum PathMode {
+    /// Join words onto the url, normalising the path
+    #[default]
+    Normal,
+    /// Send words exactly as written
+    Raw,
+    /// Percent-encode each word
+    Encode,
+    /// Percent-encode each word twice
+    DoubleEncode,
+}
+
 /// The supported output file types
 #[derive(Copy, Clone)]
 enum FileTypes {
@@ -169,36 +251,65 @@ where
     // unnecessary clones
     let mut args = app().get_matches_from(args);
 
+    // If verifying a report then the rest of the options are given to
+    // the subcommand
+    let mut verify_report = None;
+    if let Some((_, mut verify_args)) = args.remove_subcommand() {
+        verify_report = verify_args.remove_one::<PathBuf>("report");
+        args = verify_args;
+    }
+
     let mut hostnames: Vec<Url> = Vec::new();
 
-    // Get from host arguments
-    if let Some(host) = args.remove_one::<Url>("host") {
-        hostnames.push(host);
-    }
-    if let Some(host_files) = args.remove_many::<PathBuf>("host_file") {
-        for host_file in host_files {
-            let hosts = lines_from_file(&host_file);
-            for hostname in hosts {
-                if url_is_valid(&hostname).is_ok() {
-                    if let Ok(host) = Url::parse(hostname.as_str()) {
-                        hostnames.push(host);
+    // Get from host arguments, there are none when verifying a report
+    if verify_report.is_none() {
+        if let Some(host) = args.remove_one::<Url>("host") {
+            hostnames.push(host);
+        }
+        if let Some(host_files) = args.remove_many::<PathBuf>("host_file") {
+            for host_file in host_files {
+                let hosts = lines_from_file(&host_file);
+                for hostname in hosts {
+                    if url_is_valid(&hostname).is_ok() {
+                        if let Ok(host) = Url::parse(hostname.as_str()) {
+                            hostnames.push(host);
+                        }
+                    } else {
+                        println!("Invalid URL: {}", hostname);
                     }
-                } else {
-                    println!("Invalid URL: {}", hostname);
                 }
             }
         }
-    }
-    if let Some(extra_hosts) = args.remove_many::<Url>("extra_hosts") {
-        hostnames.extend(extra_hosts);
-    }
+        if let Some(extra_hosts) = args.remove_many::<Url>("extra_hosts") {
+            hostnames.extend(extra_hosts);
+        }
 
-    if hostnames.is_empty() {
-        println!("No valid hosts were provided - exiting");
-        exit(2);
+        if hostnames.is_empty() {
+            println!("No valid hosts were provided - exiting");
+            exit(2);
+        }
+        hostnames.sort();
+        hostnames.dedup();
+    }
+
+    // Get the not found rules from the arguments and from any files
+    let mut not_found_rules: Vec<NotFoundRule> = args
+        .remove_many("not_found")
+        .map(Iterator::collect)
+        .unwrap_or_default();
+    if let Some(rule_files) = args.remove_many::<PathBuf>("not_found_file") {
+        for rule_file in rule_files {
+            for line in non_empty_lines_from_file(&rule_file) {
+                match not_found_rule_parse(&line) {
+                    Ok(rule) => not_found_rules.push(rule),
+                    Err(e) => {
+                        println!("{}", e);
+                        exit(2);
+                    }
+                }
+            }
+        }
     }
-    hostnames.sort();
-    hostnames.dedup();
 
     // Parse wordlist file names into a vector
     let wordlists: Option<Vec<String>> =
@@ -282,21 +393,31 @@ where
     // Create the GlobalOpts struct and return it
     GlobalOpts {
         hostnames,
+        verify_report,
         wordlist_files: wordlists,
         prefixes: load_modifiers(&mut args, "prefixes"),
         extensions: load_modifiers(&mut args, "extensions"),
         extension_substitution: args.get_flag("extension_substitution"),
+        path_mode: *args.get_one("path_mode").expect("Must be valid path mode"),
+        add_slash: args.get_flag("add_slash"),
         max_threads: args
             .remove_one("max_threads")
             .expect("Max threads is set"),
         proxy_enabled,
         proxy_address,
+        unix_socket: args.remove_one("unix_socket"),
         proxy_auth_enabled: false,
         ignore_cert: args.get_flag("ignore_cert"),
         show_htaccess: args.get_flag("show_htaccess"),
         throttle: args.remove_one("throttle").unwrap_or_default(),
         max_recursion_depth,
         user_agent: args.remove_one("user_agent"),
+        user_agents: args
+            .remove_one::<PathBuf>("user_agent_file")
+            .map(|filename| non_empty_lines_from_file(&filename)),
+        random_headers: args
+            .remove_many("random_header")
+            .map(Iterator::collect),
         // Dependency between username and password is handled by Clap
         username: args.remove_one("username"),
         // Dependency between username and password is handled by Clap
@@ -304,7 +425,11 @@ where
         output_file: filename_from_args(&args, FileTypes::Txt),
         json_file: filename_from_args(&args, FileTypes::Json),
         xml_file: filename_from_args(&args, FileTypes::Xml),
+        report_baselines: args.get_flag("report_baselines"),
+        har_file: args.remove_one("har_file"),
+        har_body_size: args.remove_one("har_body_size"),
         timeout: args.remove_one("timeout").expect("Timeout is set"),
+        max_body_size: args.remove_one("max_body_size"),
         max_errors: args
             .remove_one::<u32>("max_errors")
             .expect("Max errors is an integer"),
@@ -314,14 +439,41 @@ where
         scan_listable: args.get_flag("scan_listable"),
         cookies,
         headers,
+        resolve: args.remove_many("resolve").map(Iterator::collect),
+        connect_to: args.remove_many("connect_to").map(Iterator::collect),
         scrape_listable: args.get_flag("scrape_listable"),
+        spider: args.get_flag("spider"),
+        js_endpoints: args.get_flag("js_endpoints"),
+        spider_depth: args
+            .remove_one("spider_depth")
+            .expect("Spider depth is set"),
+        robots_sitemap: args.get_flag("robots_sitemap"),
         whitelist,
         code_list,
         is_terminal: atty::is(Stream::Stdout),
         no_color: args.get_flag("no_color"),
         disable_validator: args.get_flag("disable_validator"),
+        not_found_rules,
+        not_found_only: args.get_flag("not_found_only"),
+        revalidate: args.remove_one("revalidate"),
+        validator_probes: args
+            .remove_one("validator_probes")
+            .expect("Validator probes is set"),
+        validator_threads: args
+            .remove_one("validator_threads")
+            .expect("Validator threads is set"),
+        wildcard_threshold: args.remove_one("wildcard_threshold"),
+        wildcard_requests: args
+            .remove_one("wildcard_requests")
+            .expect("Wildcard requests is set"),
+        size_tolerance: args
+            .remove_one("size_tolerance")
+            .expect("Size tolerance is set"),
+        detect_case: args.get_flag("detect_case"),
         http_verb: *args.get_one("http_verb").expect("Must be valid HTTP verb"),
         scan_opts,
+        bypass_403: args.get_flag("bypass_403"),
+        follow_https_upgrade: args.get_flag("follow_https_upgrade"),
         log_level,
         length_blacklist: if let Some(lengths) =
             args.get_many("length_blacklist")
@@ -330,6 +482,9 @@ where
         } else {
             Default::default()
         },
+        min_time: args.remove_one("min_time"),
+        max_time: args.remove_one("max_time"),
+        similarity: args.remove_one("similarity"),
     }
 }
 
@@ -339,7 +494,7 @@ fn app() -> Command {
     // build date in the version string. When building releases via the
     // Makefile, only use the release number.
     let version_string = get_version_string();
-    Command::new("Dirble")
+    let app = Command::new("Dirble")
         .version(version_string)
         .author(
             "Developed by Izzy Whistlecroft \
@@ -348,7 +503,9 @@ fn app() -> Command {
         .after_help("OUTPUT FORMAT:
     + [url] - File
     D [url] - Directory
-    L [url] - Listable Directory\n
+    L [url] - Listable Directory
+    ! [url] - Change in not found responses (with --revalidate)
+    W [url] - Wildcard directory (with --wildcard-threshold)\n
 EXAMPLE USE:
     - Run against a website using the default dirble_wordlist.txt from the
       current directory:
@@ -360,7 +517,9 @@ EXAMPLE USE:
     - Providing a list of extensions and a list of URIs:
         dirble [address] -X wordlists/web.lst -U uri-list.txt\n
     - Providing multiple hosts to scan via command line:
-        dirble [address] -u [address] -u [address]")
+        dirble [address] -u [address] -u [address]\n
+    - Checking whether the paths in an earlier report are still present:
+        dirble verify report.json")
         .arg_required_else_help(true)
         .arg(Arg::new("host")
              .action(ArgAction::Set)
@@ -461,6 +620,24 @@ substituted with the current extension")
             .requires("extension-options")
             .short('f')
             .long("force-extension"))
+        .arg(Arg::new("path_mode")
+             .action(ArgAction::Set)
+             .default_value("normal")
+             .display_order(32)
+             .help(
+"Specify how words are placed into the path, raw and encoded paths are
+sent without being normalised")
+             .ignore_case(true)
+             .long("path-mode")
+             .next_line_help(true)
+             .value_parser(EnumValueParser::<PathMode>::new()))
+        .arg(Arg::new("add_slash")
+             .action(ArgAction::SetTrue)
+             .display_order(32)
+             .help(
+"Also request each word with a trailing slash")
+             .long("add-slash")
+             .next_line_help(true))
         .arg(Arg::new("prefixes")
              .action(ArgAction::Append)
              .display_order(30)
@@ -506,6 +683,34 @@ per line")
              .long("xml-file")
              .next_line_help(true)
              .visible_alias("oX"))
+        .arg(Arg::new("report_baselines")
+             .action(ArgAction::SetTrue)
+             .display_order(40)
+             .help(
+"Include what nonexistent paths looked like in each scanned directory in
+the JSON and XML reports. The JSON report becomes an object with the
+paths and directories instead of a list of paths.")
+             .long("report-baselines")
+             .next_line_help(true))
+        .arg(Arg::new("har_file")
+             .action(ArgAction::Set)
+             .display_order(40)
+             .help(
+"Sets a file to write every request and response to as an HTTP Archive")
+             .long("har")
+             .next_line_help(true)
+             .value_name("har-file"))
+        .arg(Arg::new("har_body_size")
+             .action(ArgAction::Set)
+             .display_order(40)
+             .help(
+"Truncate response bodies in the HTTP Archive to the given number of
+bytes, set to 0 to leave them out")
+             .long("har-body-size")
+             .next_line_help(true)
+             .requires("har_file")
+             .value_name("bytes")
+             .value_parser(value_parser!(usize)))
         .arg(Arg::new("output_all")
              .action(ArgAction::Set)
              .display_order(41)
@@ -542,6 +747,36 @@ username and password in the form
              .long("no-proxy")
              .next_line_help(true)
             )
+        .arg(Arg::new("unix_socket")
+             .action(ArgAction::Set)
+             .display_order(51)
+             .help(
+"Connect to the path of a Unix domain socket instead of the host given
+in the URI")
+             .long("unix-socket")
+             .next_line_help(true)
+             .value_name("path")
+             .value_parser(value_parser!(PathBuf)))
+        .arg(Arg::new("resolve")
+             .action(ArgAction::Append)
+             .display_order(51)
+             .help(
+"Resolve a host and port to the given address in the form
+\"host:port:address\", can be used multiple times")
+             .long("resolve")
+             .next_line_help(true)
+             .value_name("host:port:address")
+             .value_parser(resolve_is_valid))
+        .arg(Arg::new("connect_to")
+             .action(ArgAction::Append)
+             .display_order(51)
+             .help(
+"Send requests for a host and port to a different host and port in the
+form \"host:port:connect-host:connect-port\", can be used multiple times")
+             .long("connect-to")
+             .next_line_help(true)
+             .value_name("host:port:connect-host:connect-port")
+             .value_parser(connect_to_is_valid))
         .arg(Arg::new("max_threads")
              .action(ArgAction::Set)
              .default_value("10")
@@ -623,6 +858,41 @@ recursion")
 "Enable scraping of listable directories for urls, often produces large
 amounts of output")
              .long("scrape-listable")
+             .next_line_help(true))
+        .arg(Arg::new("spider")
+             .action(ArgAction::SetTrue)
+             .display_order(80)
+             .help(
+"Follow the links, scripts, images, forms and frames in HTML pages which
+are found, adding any under the hosts being scanned to the results")
+             .long("spider")
+             .next_line_help(true))
+        .arg(Arg::new("js_endpoints")
+             .action(ArgAction::SetTrue)
+             .display_order(80)
+             .help(
+"Extract paths and API routes from JavaScript files which are found, adding
+any under the hosts being scanned to the results")
+             .long("js-endpoints")
+             .next_line_help(true))
+        .arg(Arg::new("spider_depth")
+             .default_value("3")
+             .display_order(80)
+             .help(
+"The number of links to follow away from a page found by the scan when
+spidering or extracting paths from JavaScript files")
+             .long("spider-depth")
+             .next_line_help(true)
+             .value_name("depth")
+             .value_parser(value_parser!(u32).range(1..)))
+        .arg(Arg::new("robots_sitemap")
+             .action(ArgAction::SetTrue)
+             .display_order(80)
+             .help(
+"Fetch robots.txt and sitemap.xml from each host before scanning, adding
+the paths listed in them to the results and their words to the wordlist
+for the host")
+             .long("robots-sitemap")
              .next_line_help(true)
              )
         .arg(Arg::new("cookie")
@@ -653,6 +923,28 @@ no value must end in a semicolon")
              .next_line_help(true)
              .short('a')
            )
+        .arg(Arg::new("user_agent_file")
+             .action(ArgAction::Set)
+             .conflicts_with("user_agent")
+             .display_order(90)
+             .help(
+"The name of a file containing user-agents, one per line. A random
+user-agent is picked from the file for each request")
+             .long("user-agent-file")
+             .next_line_help(true)
+             .value_name("user-agent-file")
+             .value_parser(value_parser!(PathBuf)))
+        .arg(Arg::new("random_header")
+             .action(ArgAction::Append)
+             .display_order(90)
+             .help(
+"Provide a header in the form \"header:file\", the value of the header is
+picked at random from the lines of the file for each request. Can be
+used multiple times")
+             .long("random-header")
+             .next_line_help(true)
+             .value_name("header:file")
+             .value_parser(random_header_parse))
         .arg(Arg::new("verbose")
              .action(ArgAction::Count)
              .display_order(100)
@@ -702,6 +994,108 @@ also disables detection of not found codes")
 "Disable automatic detection of not found codes")
              .long("disable-validator")
              .next_line_help(true))
+        .arg(Arg::new("not_found")
+             .action(ArgAction::Append)
+             .display_order(110)
+             .help(
+"Treat responses matching the given rule as not found, as well as any
+which are detected automatically unless --not-found-only is given. Rules
+apply to directories as well as files. Rules are comma separated conditions
+from code=<code>, size=<bytes> and regex=<pattern>, which must come last,
+and can be limited to host=<host> and paths starting with path=<path>.
+Can be used multiple times")
+             .long("not-found")
+             .next_line_help(true)
+             .value_name("rule")
+             .value_parser(not_found_rule_parse))
+        .arg(Arg::new("not_found_file")
+             .action(ArgAction::Append)
+             .display_order(110)
+             .help(
+"The name of a file containing not found rules, one per line")
+             .long("not-found-file")
+             .next_line_help(true)
+             .value_name("file")
+             .value_parser(value_parser!(PathBuf)))
+        .arg(Arg::new("not_found_only")
+             .action(ArgAction::SetTrue)
+             .display_order(110)
+             .help(
+"Only use the not found rules for the hosts and paths they apply to,
+instead of also detecting not found responses automatically")
+             .long("not-found-only")
+             .next_line_help(true))
+        .arg(Arg::new("validator_probes")
+             .default_value("3")
+             .display_order(110)
+             .help(
+"The number of random paths to request when detecting what nonexistent
+paths look like")
+             .long("validator-probes")
+             .next_line_help(true)
+             .value_name("probes")
+             .value_parser(value_parser!(u32).range(2..)))
+        .arg(Arg::new("validator_threads")
+             .default_value("4")
+             .display_order(110)
+             .help(
+"The number of directories to detect nonexistent paths for at once")
+             .long("validator-threads")
+             .next_line_help(true)
+             .value_name("threads")
+             .value_parser(value_parser!(u32).range(1..)))
+        .arg(Arg::new("wildcard_threshold")
+             .action(ArgAction::Set)
+             .display_order(110)
+             .help(
+"Stop scanning a directory if at least this percentage of the paths
+requested in it are found, reporting it as a wildcard directory instead.
+Hits are held back until the number of requests given by
+--wildcard-requests have been made")
+             .long("wildcard-threshold")
+             .next_line_help(true)
+             .value_name("percent")
+             .value_parser(value_parser!(u8).range(1..=100)))
+        .arg(Arg::new("wildcard_requests")
+             .default_value("100")
+             .display_order(110)
+             .help(
+"The number of requests each thread makes in a directory between checks of
+whether it's a wildcard directory")
+             .long("wildcard-requests")
+             .next_line_help(true)
+             .value_name("requests")
+             .value_parser(value_parser!(u64).range(1..)))
+        .arg(Arg::new("size_tolerance")
+             .default_value("16")
+             .display_order(110)
+             .help(
+"If the sizes of nonexistent paths differ by at most this many bytes then
+treat any size within this many bytes of them as not found")
+             .long("size-tolerance")
+             .next_line_help(true)
+             .value_name("bytes")
+             .value_parser(value_parser!(usize)))
+        .arg(Arg::new("revalidate")
+             .action(ArgAction::Set)
+             .conflicts_with("disable_validator")
+             .display_order(110)
+             .help(
+"Check what nonexistent paths look like again after the given number of
+requests in each thread, hits are held back until checked and changes
+are flagged in the report")
+             .long("revalidate")
+             .next_line_help(true)
+             .value_name("requests")
+             .value_parser(value_parser!(u64).range(1..)))
+        .arg(Arg::new("detect_case")
+             .action(ArgAction::SetTrue)
+             .display_order(110)
+             .help(
+"Detect directories on case insensitive servers and skip words which
+only differ by case in them")
+             .long("detect-case")
+             .next_line_help(true))
         .arg(Arg::new("scan_401")
              .action(ArgAction::SetTrue)
              .display_order(120)
@@ -716,6 +1110,22 @@ also disables detection of not found codes")
 "Scan folders if they return 403 - Forbidden frequently")
              .long("scan-403")
              .next_line_help(true))
+        .arg(Arg::new("bypass_403")
+             .action(ArgAction::SetTrue)
+             .display_order(120)
+             .help(
+"Retry paths which return 403 - Forbidden with variations of the request
+which may bypass access controls")
+             .long("bypass-403")
+             .next_line_help(true))
+        .arg(Arg::new("follow_https_upgrade")
+             .action(ArgAction::SetTrue)
+             .display_order(120)
+             .help(
+"Scan directories over HTTPS if every request to them redirects to the
+same path over HTTPS")
+             .long("follow-https-upgrade")
+             .next_line_help(true))
         .arg(Arg::new("ignore_cert")
              .action(ArgAction::SetTrue)
              .help(
@@ -735,6 +1145,15 @@ also disables detection of not found codes")
              .long("timeout")
              .next_line_help(true)
              .value_parser(value_parser!(u32)))
+        .arg(Arg::new("max_body_size")
+             .action(ArgAction::Set)
+             .help(
+"Stop downloading response bodies after the given number of bytes, the
+size reported will be taken from the Content-Length header")
+             .long("max-body-size")
+             .next_line_help(true)
+             .value_name("bytes")
+             .value_parser(value_parser!(usize)))
         .arg(Arg::new("max_errors")
              .default_value("5")
              .help(
@@ -757,8 +1176,69 @@ set to 0 to disable")
              .num_args(1..)
              .next_line_help(true)
                           .value_delimiter(','))
+        .arg(Arg::new("min_time")
+             .action(ArgAction::Set)
+             .help(
+"Hide responses which took less than the given time, in milliseconds")
+             .long("min-time")
+             .next_line_help(true)
+             .value_name("milliseconds")
+             .value_parser(value_parser!(u64)))
+        .arg(Arg::new("max_time")
+             .action(ArgAction::Set)
+             .help(
+"Hide responses which took more than the given time, in milliseconds")
+             .long("max-time")
+             .next_line_help(true)
+             .value_name("milliseconds")
+             .value_parser(value_parser!(u64)))
+        .arg(Arg::new("similarity")
+             .action(ArgAction::Set)
+             .help(
+"Treat responses as not found if their content is at least this
+percentage similar to the responses for nonexistent paths, 90 works well
+for pages containing timestamps or tokens")
+             .long("similarity")
+             .next_line_help(true)
+             .value_name("percent")
+             .value_parser(value_parser!(u8).range(1..=100)));
+
+    // The verify subcommand takes the same options as a scan, except for
+    // the hosts which come from the report instead and the JSON and XML
+    // reports, as only a text report of the verification is written
+    let verify = Command::new("verify")
+        .about(
+"Re-request the paths from a previous JSON or XML report and show which
+are still present, gone, or have changed")
+        .arg_required_else_help(true)
+        .arg(Arg::new("report")
+             .action(ArgAction::Set)
+             .help("The JSON or XML report to verify")
+             .index(1)
+             .required(true)
+             .value_name("report")
+             .value_parser(value_parser!(PathBuf)))
+        .args(app
+              .get_arguments()
+              .filter(|arg| {
+                  let id = arg.get_id().as_str();
+                  !HOST_ARGS.contains(&id) && !REPORT_ARGS.contains(&id)
+              })
+              .cloned())
+        .groups(app
+                .get_groups()
+                .filter(|group| group.get_id() != "hosts")
+                .cloned());
+
+    app.subcommand(verify).subcommand_negates_reqs(true)
 }
 
+// Arguments giving the hosts to scan, which aren't used by verify
+const HOST_ARGS: [&str; 3] = ["host", "host_file", "extra_hosts"];
+
+// Arguments for JSON and XML reports, which verify doesn't write
+const REPORT_ARGS: [&str; 3] = ["json_file", "xml_file", "output_all"];
+
 /// filetype is one of "txt", "json", and "xml". Returns a filename that is
 /// either the filename supplied by the user if the corresponding argument has
 /// been given, or if the "output_all" argument is provided then build a
@@ -775,23 +1255,26 @@ fn filename_from_args(
             }
         }
         FileTypes::Json => {
-            if let Some(json_file) = args.get_one::<String>("json_file") {
+            if let Ok(Some(json_file)) = args.try_get_one::<String>("json_file")
+            {
                 return Some(json_file.to_string());
             }
         }
         FileTypes::Xml => {
-            if let Some(xml_file) = args.get_one::<String>("xml_file") {
+            if let Ok(Some(xml_file)) = args.try_get_one::<String>("xml_file") {
                 return Some(xml_file.to_string());
             }
         }
     }
 
     // This function is called once for each filetype, so we don't remove
-    // the arg from the matcher
-    args.get_one::<String>("output_all")
-        .map(|output_all_prefix| {
+    // the arg from the matcher. The report arguments aren't defined for
+    // verify.
+    args.try_get_one::<String>("output_all").ok().flatten().map(
+        |output_all_prefix| {
             format!("{}.{}", output_all_prefix, <&'static str>::from(filetype))
-        })
+        },
+    )
 }
 
 fn load_modifiers(args: &mut clap::ArgMatches, mod_type: &str) -> Vec<String> {
@@ -869,6 +1352,168 @@ fn url_is_valid(hostname: &str) -> Result<(), String> {
     }
 }
 
+// Splits a "host:rest" string on the colon following the host, where the
+// host may be a bracketed IPv6 address
+fn split_host(entry: &str) -> Option<(&str, &str)> {
+    if entry.starts_with('[') {
+        let end = entry.find(']')?;
+        let rest = entry[end + 1..].strip_prefix(':')?;
+        Some((&entry[..=end], rest))
+    } else {
+        entry.split_once(':')
+    }
+}
+
+#[inline]
+fn port_is_valid(port: &str) -> bool {
+    port.is_empty() || port.parse::<u16>().is_ok()
+}
+
+// Checks that a --resolve entry is in the form "host:port:address"
+fn resolve_is_valid(entry: &str) -> Result<String, String> {
+    let components = split_host(entry).and_then(|(host, rest)| {
+        rest.split_once(':')
+            .map(|(port, address)| (host, port, address))
+    });
+    match components {
+        Some((host, port, address))
+            if !host.is_empty()
+                && !port.is_empty()
+                && port_is_valid(port)
+                && !address.is_empty() =>
+        {
+            Ok(entry.into())
+        }
+        _ => Err(format!(
+            "The resolve entry \"{}\" must be in the form host:port:address",
+            entry
+        )),
+    }
+}
+
+// Checks that a --connect-to entry is in the form
+// "host:port:connect-host:connect-port". As with curl, any of the
+// fields may be left empty to match or keep the original value.
+fn connect_to_is_valid(entry: &str) -> Result<String, String> {
+    let components = split_host(entry)
+        .and_then(|(_, rest)| rest.split_once(':'))
+        .and_then(|(port, destination)| {
+            split_host(destination)
+                .map(|(_, connect_port)| (port, connect_port))
+        });
+    match components {
+        Some((port, connect_port))
+            if port_is_valid(port) && port_is_valid(connect_port) =>
+        {
+            Ok(entry.into())
+        }
+        _ => Err(format!(
+            "The connect-to entry \"{}\" must be in the form \
+             host:port:connect-host:connect-port",
+            entry
+        )),
+    }
+}
+
+// Reads the lines of a file, ignoring any blank ones
+fn non_empty_lines_from_file(filename: &Path) -> Vec<String> {
+    lines_from_file(filename)
+        .into_iter()
+        .filter(|line| !line.trim().is_empty())
+        .collect()
+}
+
+// Parses a --random-header argument in the form "header:file" and loads
+// the possible values from the file
+fn random_header_parse(entry: &str) -> Result<RandomHeader, String> {
+    let Some((name, filename)) = entry.split_once(':') else {
+        return Err(format!(
+            "The random header \"{}\" must be in the form header:file",
+            entry
+        ));
+    };
+    let filename = PathBuf::from(filename);
+    if name.trim().is_empty() || !filename.is_file() {
+        return Err(format!(
+            "The random header \"{}\" must be in the form header:file",
+            entry
+        ));
+    }
+
+    let values = non_empty_lines_from_file(&filename);
+    if values.is_empty() {
+        return Err(format!(
+            "The file for random header \"{}\" contains no values",
+            name
+        ));
+    }
+
+    Ok(RandomHeader {
+        name: name.trim().into(),
+        values,
+    })
+}
+
+// Parses a not found rule in the form "key=value,key=value". The regex
+// is the rest of the rule so that it can contain commas.
+fn not_found_rule_parse(entry: &str) -> Result<NotFoundRule, String> {
+    let invalid = |reason: &str| {
+        format!("The not found rule \"{}\" is invalid: {}", entry, reason)
+    };
+
+    let mut rule = NotFoundRule {
+        host: None,
+        path: None,
+        code: None,
+        size: None,
+        regex: None,
+    };
+    let mut rest = entry.trim();
+    while !rest.is_empty() {
+        let Some((key, value)) = rest.split_once('=') else {
+            return Err(invalid("conditions must be in the form key=value"));
+        };
+        let key = key.trim();
+
+        if key == "regex" {
+            let regex =
+                Regex::new(value).map_err(|e| invalid(&e.to_string()))?;
+            rule.regex = Some(regex);
+            break;
+        }
+
+        let value = match value.split_once(',') {
+            Some((value, remaining)) => {
+                rest = remaining;
+                value
+            }
+            None => {
+                rest = "";
+                value
+            }
+        };
+        match key {
+            "host" => rule.host = Some(value.to_lowercase()),
+            "path" => rule.path = Some(value.into()),
+            "code" => {
+                rule.code =
+                    Some(value.parse().map_err(|_| invalid("invalid code"))?);
+            }
+            "size" => {
+                rule.size =
+                    Some(value.parse().map_err(|_| invalid("invalid size"))?);
+            }
+            _ => return Err(invalid(&format!("unknown key \"{}\"", key))),
+        }
+    }
+
+    if rule.code.is_none() && rule.size.is_none() && rule.regex.is_none() {
+        return Err(invalid("one of code, size or regex must be given"));
+    }
+
+    Ok(rule)
+}
+
 fn length_blacklist_parse(
     blacklist_inputs: clap::parser::ValuesRef<String>,
 ) -> LengthRanges {
@@ -924,40 +1569,70 @@ mod test {
         fn default() -> Self {
             GlobalOpts {
                 hostnames: Default::default(),
+                verify_report: Default::default(),
                 wordlist_files: Default::default(),
                 prefixes: vec!["".into()],
                 extensions: vec!["".into()],
                 extension_substitution: false,
+                path_mode: Default::default(),
+                add_slash: Default::default(),
                 max_threads: 10,
                 proxy_enabled: Default::default(),
                 proxy_address: Default::default(),
+                unix_socket: Default::default(),
                 proxy_auth_enabled: Default::default(),
                 ignore_cert: Default::default(),
                 show_htaccess: Default::default(),
                 throttle: Default::default(),
                 max_recursion_depth: Default::default(),
                 user_agent: Default::default(),
+                user_agents: Default::default(),
+                random_headers: Default::default(),
                 username: Default::default(),
                 password: Default::default(),
                 output_file: Default::default(),
                 json_file: Default::default(),
                 xml_file: Default::default(),
+                report_baselines: Default::default(),
+                har_file: Default::default(),
+                har_body_size: Default::default(),
                 timeout: 5,
+                max_body_size: Default::default(),
                 max_errors: 5,
                 wordlist_split: 3,
                 scan_listable: Default::default(),
                 cookies: Default::default(),
                 headers: Default::default(),
+                resolve: Default::default(),
+                connect_to: Default::default(),
                 scrape_listable: Default::default(),
+                spider: Default::default(),
+                js_endpoints: Default::default(),
+                spider_depth: 3,
+                robots_sitemap: Default::default(),
                 whitelist: Default::default(),
                 code_list: Default::default(),
                 is_terminal: Default::default(),
                 no_color: Default::default(),
                 disable_validator: Default::default(),
+                not_found_rules: Default::default(),
+                not_found_only: Default::default(),
+                revalidate: Default::default(),
+                validator_probes: 3,
+                validator_threads: 4,
+                wildcard_threshold: Default::default(),
+                wildcard_requests: 100,
+                size_tolerance: 16,
+                detect_case: Default::default(),
                 http_verb: Default::default(),
                 scan_opts: Default::default(),
+                bypass_403: Default::default(),
+                follow_https_upgrade: Default::default(),
                 log_level: Info,
                 length_blacklist: Default::default(),
+                min_time: Default::default(),
+                max_time: Default::default(),
+                similarity: Default::default(),
             }
         }
     }
@@ -1328,6 +2003,34 @@ mod test {
         );
     }
 
+    #[test]
+    fn path_mode() {
+        assert_args(
+            ["test", "http://some-host", "--path-mode", "raw"],
+            GlobalOpts {
+                hostnames: vec!["http://some-host".parse().unwrap()],
+                path_mode: PathMode::Raw,
+                ..Default::default()
+            },
+        );
+        assert_args(
+            ["test", "http://some-host", "--path-mode", "double-encode"],
+            GlobalOpts {
+                hostnames: vec!["http://some-host".parse().unwrap()],
+                path_mode: PathMode::DoubleEncode,
+                ..Default::default()
+            },
+        );
+        assert_args(
+            ["test", "http://some-host", "--add-slash"],
+            GlobalOpts {
+                hostnames: vec!["http://some-host".parse().unwrap()],
+                add_slash: true,
+                ..Default::default()
+            },
+        );
+    }
+
     #[test]
     fn prefixes() {
         assert_args(
@@ -1481,6 +2184,73 @@ mod test {
         );
     }
 
+    #[test]
+    fn report_baselines() {
+        assert_args(
+            ["test", "http://some-host", "--report-baselines"],
+            GlobalOpts {
+                hostnames: vec!["http://some-host".parse().unwrap()],
+                report_baselines: true,
+                ..Default::default()
+            },
+        );
+    }
+
+    #[test]
+    fn har_file() {
+        assert_args(
+            ["test", "http://some-host", "--har", "some-file.har"],
+            GlobalOpts {
+                hostnames: vec!["http://some-host".parse().unwrap()],
+                har_file: Some("some-file.har".into()),
+                ..Default::default()
+            },
+        );
+        assert_args(
+            [
+                "test",
+                "http://some-host",
+                "--har",
+                "some-file.har",
+                "--har-body-size",
+                "0",
+            ],
+            GlobalOpts {
+                hostnames: vec!["http://some-host".parse().unwrap()],
+                har_file: Some("some-file.har".into()),
+                har_body_size: Some(0),
+                ..Default::default()
+            },
+        );
+    }
+
+    #[test]
+    fn verify_report() {
+        assert_args(
+            ["test", "verify", "report.json", "--throttle", "10"],
+            GlobalOpts {
+                verify_report: Some("report.json".into()),
+                throttle: 10,
+                ..Default::default()
+            },
+        );
+
+        // Only a text report of the verification can be written
+        for arg in ["--json-file", "--xml-file", "--output-all"] {
+            assert!(
+                app()
+                    .try_get_matches_from([
+                        "test",
+                        "verify",
+                        "report.json",
+                        arg,
+                        "out"
+                    ])
+                    .is_err()
+            );
+        }
+    }
+
     #[test]
     fn output_all() {
         assert_args(
@@ -1718,6 +2488,74 @@ mod test {
         );
     }
 
+    #[test]
+    fn unix_socket() {
+        assert_args(
+            [
+                "test",
+                "http://localhost",
+                "--unix-socket",
+                "/run/some.sock",
+            ],
+            GlobalOpts {
+                hostnames: vec!["http://localhost".parse().unwrap()],
+                unix_socket: Some("/run/some.sock".into()),
+                ..Default::default()
+            },
+        );
+    }
+
+    #[test]
+    fn resolve() {
+        assert_args(
+            [
+                "test",
+                "http://some-host",
+                "--resolve",
+                "some-host:80:127.0.0.1",
+                "--resolve",
+                "some-host:443:[::1]",
+            ],
+            GlobalOpts {
+                hostnames: vec!["http://some-host".parse().unwrap()],
+                resolve: Some(vec![
+                    "some-host:80:127.0.0.1".into(),
+                    "some-host:443:[::1]".into(),
+                ]),
+                ..Default::default()
+            },
+        );
+        assert!(resolve_is_valid("some-host:80").is_err());
+        assert!(resolve_is_valid("some-host:http:127.0.0.1").is_err());
+        assert!(resolve_is_valid(":80:127.0.0.1").is_err());
+    }
+
+    #[test]
+    fn connect_to() {
+        assert_args(
+            [
+                "test",
+                "http://some-host",
+                "--connect-to",
+                "some-host:80:other-host:8080",
+                "--connect-to",
+                "::[::1]:",
+            ],
+            GlobalOpts {
+                hostnames: vec!["http://some-host".parse().unwrap()],
+                connect_to: Some(vec![
+                    "some-host:80:other-host:8080".into(),
+                    "::[::1]:".into(),
+                ]),
+                ..Default::default()
+            },
+        );
+        assert!(connect_to_is_valid("some-host:80").is_err());
+        assert!(connect_to_is_valid("some-host:80:other-host").is_err());
+        assert!(connect_to_is_valid("some-host:x:other-host:80").is_err());
+        assert!(connect_to_is_valid("[::1]:80:[::2]:8080").is_ok());
+    }
+
     #[test]
     fn user_agent() {
         assert_args(
@@ -1743,6 +2581,55 @@ mod test {
         );
     }
 
+    #[test]
+    fn user_agent_file() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(&mut file, "first agent").unwrap();
+        writeln!(&mut file).unwrap();
+        writeln!(&mut file, "second agent").unwrap();
+        assert_args(
+            [
+                "test",
+                "http://some-host",
+                "--user-agent-file",
+                &file.path().display().to_string(),
+            ],
+            GlobalOpts {
+                hostnames: vec!["http://some-host".parse().unwrap()],
+                user_agents: Some(vec![
+                    "first agent".into(),
+                    "second agent".into(),
+                ]),
+                ..Default::default()
+            },
+        );
+    }
+
+    #[test]
+    fn random_header() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(&mut file, "127.0.0.1").unwrap();
+        writeln!(&mut file, "10.0.0.1").unwrap();
+        assert_args(
+            [
+                "test",
+                "http://some-host",
+                "--random-header",
+                &format!("X-Forwarded-For:{}", file.path().display()),
+            ],
+            GlobalOpts {
+                hostnames: vec!["http://some-host".parse().unwrap()],
+                random_headers: Some(vec![RandomHeader {
+                    name: "X-Forwarded-For".into(),
+                    values: vec!["127.0.0.1".into(), "10.0.0.1".into()],
+                }]),
+                ..Default::default()
+            },
+        );
+        assert!(random_header_parse("X-Forwarded-For").is_err());
+        assert!(random_header_parse("X-Forwarded-For:/nonexistent").is_err());
+    }
+
     #[test]
     fn verbosity() {
         assert_args(
@@ -1895,6 +2782,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn detect_case() {
+        assert_args(
+            ["test", "http://some-host", "--detect-case"],
+            GlobalOpts {
+                hostnames: vec!["http://some-host".parse().unwrap()],
+                detect_case: true,
+                ..Default::default()
+            },
+        );
+    }
+
     #[test]
     fn scan_opts() {
         assert_args(
@@ -1921,6 +2820,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn bypass_403() {
+        assert_args(
+            ["test", "http://some-host", "--bypass-403"],
+            GlobalOpts {
+                hostnames: vec!["http://some-host".parse().unwrap()],
+                bypass_403: true,
+                ..Default::default()
+            },
+        );
+    }
+
     #[test]
     fn ignore_cert() {
         assert_args(
@@ -1965,6 +2876,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn max_body_size() {
+        assert_args(
+            ["test", "http://some-host", "--max-body-size", "1024"],
+            GlobalOpts {
+                hostnames: vec!["http://some-host".parse().unwrap()],
+                max_body_size: Some(1024),
+                ..Default::default()
+            },
+        );
+    }
+
     #[test]
     fn max_errors() {
         assert_args(
@@ -2019,4 +2942,248 @@ mod test {
             },
         );
     }
+
+    #[test]
+    fn response_time() {
+        assert_args(
+            [
+                "test",
+                "http://some-host",
+                "--min-time",
+                "100",
+                "--max-time",
+                "2000",
+            ],
+            GlobalOpts {
+                hostnames: vec!["http://some-host".parse().unwrap()],
+                min_time: Some(100),
+                max_time: Some(2000),
+                ..Default::default()
+            },
+        );
+    }
+
+    #[test]
+    fn similarity() {
+        assert_args(
+            ["test", "http://some-host", "--similarity", "90"],
+            GlobalOpts {
+                hostnames: vec!["http://some-host".parse().unwrap()],
+                similarity: Some(90),
+                ..Default::default()
+            },
+        );
+
+        // Everything would be similar enough to be not found
+        assert!(
+            app()
+                .try_get_matches_from([
+                    "test",
+                    "http://some-host",
+                    "--similarity",
+                    "0"
+                ])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn revalidate() {
+        assert_args(
+            ["test", "http://some-host", "--revalidate", "500"],
+            GlobalOpts {
+                hostnames: vec!["http://some-host".parse().unwrap()],
+                revalidate: Some(500),
+                ..Default::default()
+            },
+        );
+    }
+
+    #[test]
+    fn validator_probes() {
+        assert_args(
+            [
+                "test",
+                "http://some-host",
+                "--validator-probes",
+                "7",
+                "--size-tolerance",
+                "100",
+            ],
+            GlobalOpts {
+                hostnames: vec!["http://some-host".parse().unwrap()],
+                validator_probes: 7,
+                size_tolerance: 100,
+                ..Default::default()
+            },
+        );
+    }
+
+    #[test]
+    fn follow_https_upgrade() {
+        assert_args(
+            ["test", "http://some-host", "--follow-https-upgrade"],
+            GlobalOpts {
+                hostnames: vec!["http://some-host".parse().unwrap()],
+                follow_https_upgrade: true,
+                ..Default::default()
+            },
+        );
+    }
+
+    #[test]
+    fn not_found() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(&mut file, "host=other-host,size=120").unwrap();
+        assert_args(
+            [
+                "test",
+                "http://some-host",
+                "--not-found",
+                "code=200,regex=Page (not found|missing), sorry",
+                "--not-found",
+                "path=/api/,code=404",
+                "--not-found-file",
+                file.path().to_str().unwrap(),
+            ],
+            GlobalOpts {
+                hostnames: vec!["http://some-host".parse().unwrap()],
+                not_found_rules: vec![
+                    NotFoundRule {
+                        host: None,
+                        path: None,
+                        code: Some(200),
+                        size: None,
+                        regex: Some(
+                            Regex::new("Page (not found|missing), sorry")
+                                .unwrap(),
+                        ),
+                    },
+                    NotFoundRule {
+                        host: None,
+                        path: Some("/api/".into()),
+                        code: Some(404),
+                        size: None,
+                        regex: None,
+                    },
+                    NotFoundRule {
+                        host: Some("other-host".into()),
+                        path: None,
+                        code: None,
+                        size: Some(120),
+                        regex: None,
+                    },
+                ],
+                ..Default::default()
+            },
+        );
+        assert_args(
+            [
+                "test",
+                "http://some-host",
+                "--not-found",
+                "code=200",
+                "--not-found-only",
+            ],
+            GlobalOpts {
+                hostnames: vec!["http://some-host".parse().unwrap()],
+                not_found_rules: vec![NotFoundRule {
+                    host: None,
+                    path: None,
+                    code: Some(200),
+                    size: None,
+                    regex: None,
+                }],
+                not_found_only: true,
+                ..Default::default()
+            },
+        );
+        assert!(not_found_rule_parse("path=/api/").is_err());
+        assert!(not_found_rule_parse("code=abc").is_err());
+        assert!(not_found_rule_parse("status=200").is_err());
+        assert!(not_found_rule_parse("regex=(").is_err());
+    }
+
+    #[test]
+    fn validator_threads() {
+        assert_args(
+            ["test", "http://some-host", "--validator-threads", "16"],
+            GlobalOpts {
+                hostnames: vec!["http://some-host".parse().unwrap()],
+                validator_threads: 16,
+                ..Default::default()
+            },
+        );
+    }
+
+    #[test]
+    fn wildcard_threshold() {
+        assert_args(
+            [
+                "test",
+                "http://some-host",
+                "--wildcard-threshold",
+                "90",
+                "--wildcard-requests",
+                "50",
+            ],
+            GlobalOpts {
+                hostnames: vec!["http://some-host".parse().unwrap()],
+                wildcard_threshold: Some(90),
+                wildcard_requests: 50,
+                ..Default::default()
+            },
+        );
+    }
+
+    #[test]
+    fn spider() {
+        assert_args(
+            ["test", "http://some-host", "--spider"],
+            GlobalOpts {
+                hostnames: vec!["http://some-host".parse().unwrap()],
+                spider: true,
+                ..Default::default()
+            },
+        );
+        assert_args(
+            [
+                "test",
+                "http://some-host",
+                "--spider",
+                "--spider-depth",
+                "1",
+            ],
+            GlobalOpts {
+                hostnames: vec!["http://some-host".parse().unwrap()],
+                spider: true,
+                spider_depth: 1,
+                ..Default::default()
+            },
+        );
+    }
+
+    #[test]
+    fn js_endpoints() {
+        assert_args(
+            ["test", "http://some-host", "--js-endpoints"],
+            GlobalOpts {
+                hostnames: vec!["http://some-host".parse().unwrap()],
+                js_endpoints: true,
+                ..Default::default()
+            },
+        );
+    }
+
+    #[test]
+    fn robots_sitemap() {
+        assert_args(
+            ["test", "http://some-host", "--robots-sitemap"],
+            GlobalOpts {
+                hostnames: vec!["http://some-host".parse().unwrap()],
+                robots_sitemap: true,
+                ..Default::default()
+            },
+        );
+    }
 }
@@ -0,0 +1,301 @@
+// This file is part of Dirble - https://www.github.com/nccgroup/dirble
+// Copyright (C) 2019 Izzy Whistlecroft <Izzy(dot)Whistlecroft(at)nccgroup(dot)com>
+// Released as open source by NCC Group Plc - https://www.nccgroup.com/
+//
+// Dirble is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Dirble is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    arg_parse::GlobalOpts,
+    request::{self, Collector, RequestResponse},
+    request_thread::should_send_response,
+    validator_thread::TargetValidator,
+};
+use curl::easy::Easy2;
+use log::debug;
+use url::{Position, Url};
+
+// A variation of a request which may get around a 403 response
+#[derive(Debug, PartialEq)]
+struct Mutation {
+    technique: &'static str,
+    target: String,
+    headers: Vec<String>,
+}
+
+// Returns true if 403 bypasses should be attempted for the response.
+// Hidden .ht files are skipped as they are expected to be forbidden, as
+// are responses which the validator considers not found.
+pub fn should_probe(
+    global_opts: &GlobalOpts,
+    response: &RequestResponse,
+    validator: &Option<TargetValidator>,
+) -> bool {
+    global_opts.bypass_403
+        && response.code == 403
+        && (global_opts.show_htaccess || !response.url.path().contains("/.ht"))
+        && !validator.as_ref().is_some_and(|v| v.is_not_found(response))
+}
+
+// Returns the target and headers of the request which got around a 403,
+// as recorded by probe_403, so that it can be made again
+pub fn bypass_request(
+    response: &RequestResponse,
+) -> Option<(String, Vec<String>)> {
+    response.bypass_technique.as_ref()?;
+    Some((
+        response.requested_target.clone()?,
+        response.extra_headers.clone(),
+    ))
+}
+
+// Retries a path which returned a 403 with each of the mutations, and
+// returns the responses of any which behaved differently. The returned
+// responses keep the url of the forbidden path, with the technique, the
+// target actually requested and the headers added recorded on them.
+pub fn probe_403(
+    easy: &mut Easy2<Collector>,
+    forbidden: &RequestResponse,
+    global_opts: &GlobalOpts,
+    validator: &Option<TargetValidator>,
+) -> Vec<RequestResponse> {
+    debug!("Attempting to bypass 403 for {}", forbidden.url);
+    let mut bypasses = Vec::new();
+
+    // Techniques which rewrite the url request the root of the host, so
+    // responses which are the same as for the root itself are ignored
+    let root_target = forbidden.url[..Position::BeforePath].to_string() + "/";
+    let mut root_response = None;
+
+    for mutation in mutations(&forbidden.url) {
+        let mut response = request::make_custom_request(
+            easy,
+            &mutation.target,
+            &mutation.headers,
+            forbidden.url.clone(),
+        );
+        request::throttle(global_opts);
+
+        if response.code == forbidden.code
+            && response.content_len == forbidden.content_len
+        {
+            continue;
+        }
+
+        if mutation.target == root_target && !mutation.headers.is_empty() {
+            let root = root_response.get_or_insert_with(|| {
+                let root = request::make_custom_request(
+                    easy,
+                    &root_target,
+                    &[],
+                    forbidden.url.clone(),
+                );
+                request::throttle(global_opts);
+                root
+            });
+            if response.code == root.code
+                && response.content_len == root.content_len
+            {
+                continue;
+            }
+        }
+
+        response.is_directory = false;
+        response.is_listable = false;
+        response.source = format!("403 bypass ({})", mutation.technique);
+        if !mutation.headers.is_empty() {
+            response.source +=
+                &format!(" with {}", mutation.headers.join(", "));
+        }
+        response.bypass_technique = Some(mutation.technique.into());
+        response.requested_target = Some(mutation.target);
+        response.extra_headers = mutation.headers;
+
+        if should_send_response(global_opts, &response, validator) {
+            bypasses.push(response);
+        }
+    }
+
+    bypasses
+}
+
+// Generates the mutations of the given url to try
+fn mutations(url: &Url) -> Vec<Mutation> {
+    let origin = &url[..Position::BeforePath];
+    let path = url.path();
+    let mut mutations = Vec::new();
+
+    // Split the path into its parent and the name of the file or
+    // directory, keeping any trailing slash
+    let trimmed = path.trim_end_matches('/');
+    let trailing = if path.len() > trimmed.len() && !trimmed.is_empty() {
+        "/"
+    } else {
+        ""
+    };
+
+    if let Some((parent, name)) = trimmed.rsplit_once('/') {
+        let mutate_name = |technique, new_name: &str| Mutation {
+            technique,
+            target: format!("{}{}/{}{}", origin, parent, new_name, trailing),
+            headers: Vec::new(),
+        };
+
+        mutations.push(mutate_name("dot segment", &format!("%2e/{}", name)));
+        mutations.push(Mutation {
+            technique: "trailing ;/",
+            target: format!("{}{};/", origin, trimmed),
+            headers: Vec::new(),
+        });
+        mutations.push(Mutation {
+            technique: "trailing ..;/",
+            target: format!("{}{}..;/", origin, trimmed),
+            headers: Vec::new(),
+        });
+
+        let uppercase = name.to_uppercase();
+        let swapped = if uppercase != name {
+            uppercase
+        } else {
+            name.to_lowercase()
+        };
+        if swapped != name {
+            mutations.push(mutate_name("case change", &swapped));
+        }
+
+        // Encode the first character twice, so that it is only decoded
+        // by a server which decodes paths more than once
+        if let Some(first) = name.chars().next().filter(char::is_ascii) {
+            let double_encoded =
+                format!("%25{:02X}{}", first as u8, &name[1..]);
+            mutations.push(mutate_name("double encoding", &double_encoded));
+        }
+    }
+
+    for header in ["X-Original-URL", "X-Rewrite-URL"] {
+        mutations.push(Mutation {
+            technique: "url rewrite header",
+            target: format!("{}/", origin),
+            headers: vec![format!("{}: {}", header, path)],
+        });
+    }
+
+    mutations.push(Mutation {
+        technique: "client address header",
+        target: url.to_string(),
+        headers: vec!["X-Forwarded-For: 127.0.0.1".into()],
+    });
+
+    mutations
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_mutations() {
+        let url = Url::parse("http://example.com/dir/admin/").unwrap();
+        let targets: Vec<(String, Vec<String>)> = mutations(&url)
+            .into_iter()
+            .map(|mutation| (mutation.target, mutation.headers))
+            .collect();
+
+        assert_eq!(
+            targets,
+            vec![
+                ("http://example.com/dir/%2e/admin/".into(), vec![]),
+                ("http://example.com/dir/admin;/".into(), vec![]),
+                ("http://example.com/dir/admin..;/".into(), vec![]),
+                ("http://example.com/dir/ADMIN/".into(), vec![]),
+                ("http://example.com/dir/%2561dmin/".into(), vec![]),
+                (
+                    "http://example.com/".into(),
+                    vec!["X-Original-URL: /dir/admin/".into()]
+                ),
+                (
+                    "http://example.com/".into(),
+                    vec!["X-Rewrite-URL: /dir/admin/".into()]
+                ),
+                (
+                    "http://example.com/dir/admin/".into(),
+                    vec!["X-Forwarded-For: 127.0.0.1".into()]
+                ),
+            ]
+        );
+
+        // Only the header techniques apply to the root of a host
+        let url = Url::parse("http://example.com/").unwrap();
+        assert_eq!(mutations(&url).len(), 3);
+    }
+
+    #[test]
+    fn test_should_probe() {
+        let global_opts = GlobalOpts {
+            bypass_403: true,
+            ..Default::default()
+        };
+        let forbidden = RequestResponse {
+            code: 403,
+            content_len: 100,
+            ..RequestResponse::new(
+                Url::parse("http://example.com/admin/").unwrap(),
+            )
+        };
+
+        assert!(should_probe(&global_opts, &forbidden, &None));
+
+        // A directory where every path is forbidden isn't probed
+        let validator = TargetValidator::new(403, None, None, None, None);
+        assert!(!should_probe(&global_opts, &forbidden, &Some(validator)));
+
+        let hidden = RequestResponse {
+            code: 403,
+            ..RequestResponse::new(
+                Url::parse("http://example.com/.htaccess").unwrap(),
+            )
+        };
+        assert!(!should_probe(&global_opts, &hidden, &None));
+    }
+
+    #[test]
+    fn test_bypass_request() {
+        let mut response = RequestResponse::new(
+            Url::parse("http://example.com/admin/").unwrap(),
+        );
+        assert_eq!(bypass_request(&response), None);
+
+        // Raw paths record their target but aren't bypasses
+        response.requested_target = Some("http://example.com/ADMIN/".into());
+        assert_eq!(bypass_request(&response), None);
+
+        response.bypass_technique = Some("case change".into());
+        assert_eq!(
+            bypass_request(&response),
+            Some(("http://example.com/ADMIN/".into(), vec![]))
+        );
+
+        // Headers are replayed as they were sent, whatever they contain
+        response.bypass_technique = Some("url rewrite header".into());
+        response.requested_target = Some("http://example.com/".into());
+        response.extra_headers = vec!["X-Original-URL: /a, b)/".into()];
+        assert_eq!(
+            bypass_request(&response),
+            Some((
+                "http://example.com/".into(),
+                vec!["X-Original-URL: /a, b)/".into()]
+            ))
+        );
+    }
+}
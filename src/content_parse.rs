@@ -15,68 +15,339 @@
 // You should have received a copy of the GNU General Public License
 // along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
 
+use regex::Regex;
 use select::{document::Document, predicate::Name};
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::LazyLock,
+};
+use url::Url;
 
-// Returns complete URLs based on the contents of a listable folder
-pub fn scrape_urls(content: String, original_url: String) -> Vec<String> {
-    let mut output_urls: Vec<String> = Vec::new();
-    let mut scraped_urls: Vec<String> = Vec::new();
-
-    // Get the contents of href attributes from the given response
-    // content
-    Document::from_read(content.as_bytes())
-        .unwrap()
-        .find(Name("a"))
-        .filter_map(|n| n.attr("href"))
-        .for_each(|scraped_url| scraped_urls.push(String::from(scraped_url)));
-
-    // Parse urls and add them to the list of urls to return
-    for scraped_url in scraped_urls {
-        let complete_url;
-
-        // If a url starts with of these then it is potentially a parent
-        // or a mechanism for sorting the directory not of interest or
-        // may cause issues when scanning so are skipped
-        if scraped_url.starts_with("../")
-            || scraped_url.starts_with('?')
-            || scraped_url.starts_with("./")
+// Matches quoted strings in scripts which look like paths or urls
+static JS_ENDPOINT_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"["'`]((?:https?://[^"'`\s<>]+)|(?:\.{0,2}/[\w\-.~%/]+)|(?:[\w\-]+/[\w\-.~%/]+)|(?:[\w\-]+\.(?:php|asp|aspx|jsp|json|action|html|js|txt|xml)))(?:[?#][^"'`\s]*)?["'`]"#,
+    )
+    .unwrap()
+});
+
+// Content types look like relative paths but aren't worth requesting
+static MIME_TYPE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(?:application|audio|font|image|multipart|text|video)/[\w\-.+]+$",
+    )
+    .unwrap()
+});
+
+// The elements and attributes which link to other urls in HTML pages
+const SPIDER_LINKS: [(&str, &str); 6] = [
+    ("a", "href"),
+    ("link", "href"),
+    ("script", "src"),
+    ("img", "src"),
+    ("form", "action"),
+    ("iframe", "src"),
+];
+
+// Returns the urls linked to from an HTML page, resolved against the url
+// of the page. Queries and fragments are removed as only the paths are
+// scanned.
+pub fn spider_urls(content: &str, page_url: &Url) -> Vec<Url> {
+    let document = Document::from(content);
+    let mut urls: Vec<Url> = Vec::new();
+
+    for (element, attribute) in SPIDER_LINKS {
+        for link in document
+            .find(Name(element))
+            .filter_map(|n| n.attr(attribute))
         {
+            let Ok(mut url) = page_url.join(link.trim()) else {
+                continue;
+            };
+            if url.scheme() != "http" && url.scheme() != "https" {
+                continue;
+            }
+            url.set_query(None);
+            url.set_fragment(None);
+            if !urls.contains(&url) {
+                urls.push(url);
+            }
+        }
+    }
+
+    urls
+}
+
+// Returns the paths and urls in string literals in a script, resolved
+// against the url of the page which loaded it. This looks for absolute
+// and relative paths, paths with a directory, and files with common web
+// extensions.
+pub fn js_endpoints(content: &str, page_url: &Url) -> Vec<Url> {
+    let mut urls: Vec<Url> = Vec::new();
+
+    for captures in JS_ENDPOINT_REGEX.captures_iter(content) {
+        let endpoint = &captures[1];
+        if MIME_TYPE_REGEX.is_match(endpoint) {
             continue;
         }
-        // The scraped url is a path from the base URL
-        else if scraped_url.starts_with('/') {
-            // need to get the base address from the original url and
-            // append this
-            let start_index = if original_url.starts_with("https://") {
-                8
-            } else {
-                7
-            };
-            let end_index = original_url[start_index..].find('/').unwrap();
-            complete_url = format!(
-                "{}{}",
-                &original_url[0..end_index + start_index],
-                scraped_url
-            );
+
+        let Ok(mut url) = page_url.join(endpoint) else {
+            continue;
+        };
+        if url.scheme() != "http" && url.scheme() != "https" {
+            continue;
         }
-        // Where the URL is a complete url that doesn't need modifying
-        else if scraped_url.contains("://") {
-            complete_url = scraped_url;
+        url.set_query(None);
+        url.set_fragment(None);
+        if !urls.contains(&url) {
+            urls.push(url);
         }
-        // Relative paths from the current directory
-        else {
-            complete_url = format!("{}{}", original_url, scraped_url);
+    }
+
+    urls
+}
+
+// Returns the paths and the sitemaps listed in a robots.txt file. Paths
+// are cut off at the first wildcard as they can't be requested as is.
+pub fn robots_entries(content: &str) -> (Vec<String>, Vec<String>) {
+    let mut paths = Vec::new();
+    let mut sitemaps = Vec::new();
+
+    for line in content.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+
+        match key.trim().to_lowercase().as_str() {
+            "allow" | "disallow" => {
+                let path = value.split(['*', '$']).next().unwrap_or_default();
+                if !path.is_empty() && path != "/" {
+                    paths.push(String::from(path));
+                }
+            }
+            "sitemap" if !value.is_empty() => {
+                sitemaps.push(String::from(value));
+            }
+            _ => {}
         }
+    }
 
-        // Only add to the list if it's a subdirectory of the current
-        // directory
-        // And if the current directory doesn't begin with it
-        if !original_url.starts_with(&complete_url)
-            && complete_url.starts_with(&original_url)
-        {
-            output_urls.push(complete_url);
+    (paths, sitemaps)
+}
+
+// Returns the sitemaps listed in a sitemap index, and the pages listed
+// in a sitemap
+pub fn sitemap_entries(content: &str) -> (Vec<String>, Vec<String>) {
+    let mut sitemaps = Vec::new();
+    let mut pages = Vec::new();
+
+    for loc in Document::from(content).find(Name("loc")) {
+        let location = loc.text().trim().to_string();
+        if location.is_empty() {
+            continue;
+        }
+        if loc.parent().and_then(|parent| parent.name()) == Some("sitemap") {
+            sitemaps.push(location);
+        } else {
+            pages.push(location);
         }
     }
 
-    output_urls
+    (sitemaps, pages)
+}
+
+// Generates a simhash of the words in the given content. Content which
+// only differs in a few words, such as a timestamp or a token, gives
+// hashes which only differ in a few bits.
+// DefaultHasher isn't guaranteed to be the same between builds of Rust,
+// so these hashes must never be persisted or compared across runs.
+pub fn simhash(content: &str) -> u64 {
+    let mut weights = [0i64; 64];
+
+    for word in content
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+    {
+        let mut hasher = DefaultHasher::new();
+        word.to_lowercase().hash(&mut hasher);
+        let hash = hasher.finish();
+
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            if hash & (1 << bit) != 0 {
+                *weight += 1;
+            } else {
+                *weight -= 1;
+            }
+        }
+    }
+
+    weights
+        .iter()
+        .enumerate()
+        .filter(|(_, weight)| **weight > 0)
+        .fold(0, |hash, (bit, _)| hash | (1 << bit))
+}
+
+// Returns the percentage of bits which are the same in two simhashes
+pub fn similarity(a: u64, b: u64) -> u8 {
+    (100 * (64 - (a ^ b).count_ones()) / 64) as u8
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn spider_links() {
+        let page = Url::parse("http://example.com/app/index.html").unwrap();
+        let content = r#"<html><head>
+<link rel="stylesheet" href="/static/site.css">
+<script src="js/app.js"></script>
+</head><body>
+<a href=" ../about.html?tab=2#team ">About</a>
+<a href="/static/site.css">Styles</a>
+<a href="mailto:admin@example.com">Mail</a>
+<a href="javascript:void(0)">Nothing</a>
+<a href="https://other.example.com/">Other</a>
+<img src="images/logo.png">
+<form action="search.php" method="get"></form>
+<iframe src="//example.com/embed/"></iframe>
+</body></html>"#;
+
+        let urls: Vec<String> = spider_urls(content, &page)
+            .iter()
+            .map(Url::to_string)
+            .collect();
+        assert_eq!(
+            urls,
+            [
+                "http://example.com/about.html",
+                "http://example.com/static/site.css",
+                "https://other.example.com/",
+                "http://example.com/app/js/app.js",
+                "http://example.com/app/images/logo.png",
+                "http://example.com/app/search.php",
+                "http://example.com/embed/",
+            ]
+        );
+    }
+
+    #[test]
+    fn js_strings() {
+        let page = Url::parse("http://example.com/app/index.html").unwrap();
+        let content = r#"
+const api = "/api/v1/users";
+// Interpolated paths can't be requested as they are
+fetch(`/api/v1/users/${id}/posts?page=${page}`);
+fetch(`/api/v1/search?q=${query}`);
+import { render } from './lib/render.js';
+xhr.open("GET", "../admin/settings.php?tab=2");
+const full = 'https://cdn.example.com/assets/app.js';
+const page = "login.aspx";
+headers["Content-Type"] = "application/json";
+if (type === "text/html" || type === 'image/svg+xml') {}
+const message = "Hello world";
+"#;
+
+        let urls: Vec<String> = js_endpoints(content, &page)
+            .iter()
+            .map(Url::to_string)
+            .collect();
+        assert_eq!(
+            urls,
+            [
+                "http://example.com/api/v1/users",
+                "http://example.com/api/v1/search",
+                "http://example.com/app/lib/render.js",
+                "http://example.com/admin/settings.php",
+                "https://cdn.example.com/assets/app.js",
+                "http://example.com/app/login.aspx",
+            ]
+        );
+    }
+
+    #[test]
+    fn robots() {
+        let content = "# Keep crawlers out of the admin pages
+User-agent: *
+Disallow: /admin/ # not for robots
+Disallow: /private*/files
+Allow: /*.php$
+disallow: /search$
+ALLOW: /
+Disallow:
+Crawl-delay: 10
+#Disallow: /commented/
+Sitemap: https://example.com/sitemap.xml
+sitemap:   /news-sitemap.xml
+";
+
+        let (paths, sitemaps) = robots_entries(content);
+        assert_eq!(paths, ["/admin/", "/private", "/search"]);
+        assert_eq!(
+            sitemaps,
+            ["https://example.com/sitemap.xml", "/news-sitemap.xml"]
+        );
+    }
+
+    #[test]
+    fn sitemap() {
+        let content = r#"<?xml version="1.0" encoding="UTF-8"?>
+<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <sitemap>
+    <loc>
+      https://example.com/sitemap-pages.xml
+    </loc>
+  </sitemap>
+</sitemapindex>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <url><loc>https://example.com/search?q=a&amp;page=2</loc></url>
+  <url>
+    <loc>	https://example.com/pages/docs/ </loc>
+    <lastmod>2026-10-01</lastmod>
+  </url>
+  <url><loc>   </loc></url>
+</urlset>"#;
+
+        let (sitemaps, pages) = sitemap_entries(content);
+        assert_eq!(sitemaps, ["https://example.com/sitemap-pages.xml"]);
+        assert_eq!(
+            pages,
+            [
+                "https://example.com/search?q=a&page=2",
+                "https://example.com/pages/docs/",
+            ]
+        );
+    }
+
+    #[test]
+    fn simhash_empty() {
+        assert_eq!(simhash(""), 0);
+        assert_eq!(simhash("  <> -- \n"), 0);
+        assert_eq!(similarity(simhash(""), simhash("")), 100);
+    }
+
+    #[test]
+    fn simhash_identical() {
+        let page = "<html><body><p>Page not found</p></body></html>";
+        assert_eq!(simhash(page), simhash(page));
+        assert_eq!(similarity(simhash(page), simhash(page)), 100);
+        // Case and punctuation between words are ignored
+        assert_eq!(simhash("Page not found"), simhash("page, NOT found."));
+    }
+
+    #[test]
+    fn simhash_small_change() {
+        let words = "the page you requested could not be found on this \
+            server please check the address and try again or return to \
+            the home page to find what you were looking for";
+        let a = simhash(&format!("{words} token 3f9a0c request 1760781600"));
+        let b = simhash(&format!("{words} token 81be27 request 1760781642"));
+        assert!(similarity(a, b) >= 75);
+        assert_eq!(similarity(a, !a), 0);
+        assert_eq!(similarity(0, 1), 98);
+    }
 }
@@ -0,0 +1,376 @@
+// This file is part of Dirble - https://www.github.com/nccgroup/dirble
+// Copyright (C) 2019 Izzy Whistlecroft <Izzy(dot)Whistlecroft(at)nccgroup(dot)com>
+// Released as open source by NCC Group Plc - https://www.nccgroup.com/
+//
+// Dirble is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Dirble is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    arg_parse::GlobalOpts,
+    request::{self, Collector, RequestResponse},
+    request_thread,
+    validator_thread::{self, TargetValidator},
+    wordlist::UriGenerator,
+};
+use curl::easy::Easy2;
+use log::warn;
+use time::{OffsetDateTime, macros::format_description};
+use url::Url;
+
+// The number of identical hits in a row which cause the not found
+// responses to be checked straight away. The response is then treated as
+// not found, as it's likely a WAF or maintenance page that's returned for
+// every path, even if random paths still give the old response.
+const IDENTICAL_HIT_BURST: usize = 10;
+
+// Periodically re-checks what a not found response looks like for the
+// paths being scanned by a request thread. Hits are held back until the
+// next check so that any found while the target was behaving differently
+// can be filtered out.
+pub struct DriftMonitor {
+    base: Url,
+    prefix: String,
+    extension: String,
+    interval: u64,
+    requests_since_check: u64,
+    window_start: OffsetDateTime,
+    pending: Vec<RequestResponse>,
+    last_hit: Option<(u32, usize)>,
+    identical_hits: usize,
+    // The code and size of responses found to be returned for every path
+    burst_hits: Vec<(u32, usize)>,
+}
+
+impl DriftMonitor {
+    // Returns None if re-validation is disabled or there is no validator
+    // to compare against
+    pub fn new(
+        uri_gen: &UriGenerator,
+        global_opts: &GlobalOpts,
+    ) -> Option<Self> {
+        let interval = global_opts.revalidate?;
+        uri_gen.validator.as_ref()?;
+
+        Some(DriftMonitor {
+            base: uri_gen.base.clone(),
+            prefix: uri_gen.prefix.clone(),
+            extension: uri_gen.suffix.clone(),
+            interval,
+            requests_since_check: 0,
+            window_start: OffsetDateTime::now_utc(),
+            pending: Vec::new(),
+            last_hit: None,
+            identical_hits: 0,
+            burst_hits: Vec::new(),
+        })
+    }
+
+    // Holds on to a hit until the next check
+    pub fn hold(&mut self, response: RequestResponse) {
+        let hit = (response.code, response.content_len);
+        if self.burst_hits.contains(&hit) {
+            return;
+        }
+        if self.last_hit == Some(hit) {
+            self.identical_hits += 1;
+        } else {
+            self.last_hit = Some(hit);
+            self.identical_hits = 1;
+        }
+        self.pending.push(response);
+    }
+
+    // Counts a request, returning true if a check is due
+    pub fn tick(&mut self) -> bool {
+        self.requests_since_check += 1;
+        self.requests_since_check >= self.interval
+            || self.identical_hits >= IDENTICAL_HIT_BURST
+    }
+
+    // Probes for the current not found response, replacing the validator
+    // if it has changed or there was a burst of identical hits. Returns the
+    // held hits which are still valid, along with entries marking the
+    // change or burst if there was one.
+    pub fn check(
+        &mut self,
+        easy: &mut Easy2<Collector>,
+        global_opts: &GlobalOpts,
+        validator: &mut Option<TargetValidator>,
+    ) -> Vec<RequestResponse> {
+        let mut output = Vec::new();
+        let window_end = OffsetDateTime::now_utc();
+
+        let burst = self
+            .last_hit
+            .filter(|_| self.identical_hits >= IDENTICAL_HIT_BURST);
+
+        let new_validator = validator_thread::get_validator(
+            self.base.clone(),
+            &self.prefix,
+            &self.extension,
+            easy,
+            global_opts,
+        );
+
+        // If the probes errored then keep the current validator
+        let mut changed = false;
+        if let (Some(old), Some(new)) =
+            (validator.as_ref(), new_validator.as_ref())
+            && !old.is_equivalent(new)
+        {
+            changed = true;
+            let description = format!(
+                "{} to {} between {} and {}",
+                old.summary_text(),
+                new.summary_text(),
+                format_time(self.window_start),
+                format_time(window_end)
+            );
+            warn!(
+                "Nonexistent paths for {}{}*{} changed from {}",
+                self.base, self.prefix, self.extension, description
+            );
+            output.push(self.drift_entry(description));
+        }
+
+        if let Some((code, size)) = burst {
+            let description = format!(
+                "{} identical responses (CODE:{}|SIZE:{}) between {} and {}",
+                self.identical_hits,
+                code,
+                size,
+                format_time(self.window_start),
+                format_time(window_end)
+            );
+            warn!(
+                "Paths in {}{}*{} gave {}, treating them as not found",
+                self.base, self.prefix, self.extension, description
+            );
+            output.push(self.drift_entry(description));
+            self.burst_hits.push((code, size));
+        }
+
+        if changed || burst.is_some() {
+            // Re-baseline on the fresh probes, then drop the hits which
+            // look like the new not found response or the burst as they
+            // were caused by the change
+            if new_validator.is_some() {
+                *validator = new_validator;
+            }
+            let burst_hits = &self.burst_hits;
+            self.pending.retain(|response| {
+                !burst_hits.contains(&(response.code, response.content_len))
+                    && request_thread::should_send_response(
+                        global_opts,
+                        response,
+                        validator,
+                    )
+            });
+        }
+
+        output.append(&mut self.pending);
+        self.requests_since_check = 0;
+        self.window_start = window_end;
+        self.last_hit = None;
+        self.identical_hits = 0;
+
+        output
+    }
+
+    // An entry for the report marking a change in the not found responses
+    fn drift_entry(&self, description: String) -> RequestResponse {
+        let mut drift = request::fabricate_request_response(
+            self.base.clone(),
+            false,
+            false,
+        );
+        drift.found_from_listable = false;
+        drift.baseline_drift = true;
+        drift.source = description;
+        drift
+    }
+}
+
+#[inline]
+fn format_time(time: OffsetDateTime) -> String {
+    time.format(format_description!("[hour]:[minute]:[second]"))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::arg_parse::PathMode;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_drift_detected() {
+        let port = crate::test_server::launch();
+        let base = Url::parse(&format!("http://localhost:{port}/")).unwrap();
+        let global_opts = Arc::new(GlobalOpts {
+            revalidate: Some(2),
+            ..Default::default()
+        });
+
+        // The validator was created when nonexistent paths gave a 200
+        let validator = TargetValidator::new(200, Some(9), None, None, None);
+        let uri_gen = UriGenerator::new(
+            base.clone(),
+            String::new(),
+            String::new(),
+            Arc::new(vec!["ok".into()]),
+            0,
+            1,
+            0,
+            0,
+            Some(validator),
+            false,
+            PathMode::Normal,
+            false,
+        );
+        let mut validator = uri_gen.validator.clone();
+        let mut monitor = DriftMonitor::new(&uri_gen, &global_opts).unwrap();
+
+        // Hits are held until the check is due
+        let mut easy = request::generate_easy(&global_opts, &None);
+        for path in ["ok", "missing"] {
+            let response =
+                request::make_request(&mut easy, base.join(path).unwrap());
+            monitor.hold(response);
+        }
+        assert!(!monitor.tick());
+        assert!(monitor.tick());
+
+        // Now they give a 404, so the hit on a missing path is dropped
+        let output = monitor.check(&mut easy, &global_opts, &mut validator);
+        assert_eq!(output.len(), 2);
+        assert!(output[0].baseline_drift);
+        assert!(
+            output[0]
+                .source
+                .starts_with("(CODE:200|SIZE:9) to (CODE:404)")
+        );
+        assert_eq!(output[1].url, base.join("ok").unwrap());
+        assert_eq!(validator.as_ref().unwrap().summary_text(), "(CODE:404)");
+
+        // Without a change the hits are just released
+        let response =
+            request::make_request(&mut easy, base.join("ok").unwrap());
+        monitor.hold(response);
+        let output = monitor.check(&mut easy, &global_opts, &mut validator);
+        assert_eq!(output.len(), 1);
+        assert!(!output[0].baseline_drift);
+    }
+
+    #[test]
+    fn test_identical_hit_burst() {
+        let port = crate::test_server::launch();
+        let base = Url::parse(&format!("http://localhost:{port}/")).unwrap();
+        let global_opts = Arc::new(GlobalOpts {
+            revalidate: Some(1000),
+            ..Default::default()
+        });
+
+        let mut easy = request::generate_easy(&global_opts, &None);
+        let validator = validator_thread::get_validator(
+            base.clone(),
+            "",
+            "",
+            &mut easy,
+            &global_opts,
+        );
+        let uri_gen = UriGenerator::new(
+            base.clone(),
+            String::new(),
+            String::new(),
+            Arc::new(Vec::new()),
+            0,
+            1,
+            0,
+            0,
+            validator,
+            false,
+            PathMode::Normal,
+            false,
+        );
+        let mut validator = uri_gen.validator.clone();
+        let mut monitor = DriftMonitor::new(&uri_gen, &global_opts).unwrap();
+
+        // Random paths still give a 404, but the burst is flagged and
+        // treated as not found
+        let ok = request::make_request(&mut easy, base.join("ok").unwrap());
+        for _ in 0..IDENTICAL_HIT_BURST {
+            monitor.hold(ok.clone());
+        }
+        assert!(monitor.tick());
+        let output = monitor.check(&mut easy, &global_opts, &mut validator);
+        assert_eq!(output.len(), 1);
+        assert!(output[0].baseline_drift);
+        assert!(
+            output[0]
+                .source
+                .starts_with("10 identical responses (CODE:200|SIZE:10)")
+        );
+        assert_eq!(validator.as_ref().unwrap().summary_text(), "(CODE:404)");
+
+        // Later responses like it aren't reported
+        monitor.hold(ok);
+        let output = monitor.check(&mut easy, &global_opts, &mut validator);
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn test_varying_sizes_not_drift() {
+        let port = crate::test_server::launch();
+        let base =
+            Url::parse(&format!("http://localhost:{port}/varying/")).unwrap();
+        let global_opts = Arc::new(GlobalOpts {
+            revalidate: Some(1),
+            size_tolerance: 200,
+            ..Default::default()
+        });
+
+        // Each round of probes gets a different range of sizes
+        let mut easy = request::generate_easy(&global_opts, &None);
+        let validator = validator_thread::get_validator(
+            base.clone(),
+            "",
+            "",
+            &mut easy,
+            &global_opts,
+        );
+        assert_eq!(
+            validator.as_ref().unwrap().summary_text(),
+            "(CODE:200|SIZE:800-1300)"
+        );
+        let uri_gen = UriGenerator::new(
+            base,
+            String::new(),
+            String::new(),
+            Arc::new(Vec::new()),
+            0,
+            1,
+            0,
+            0,
+            validator,
+            false,
+            PathMode::Normal,
+            false,
+        );
+        let mut validator = uri_gen.validator.clone();
+        let mut monitor = DriftMonitor::new(&uri_gen, &global_opts).unwrap();
+
+        let output = monitor.check(&mut easy, &global_opts, &mut validator);
+        assert!(output.is_empty());
+    }
+}
@@ -0,0 +1,461 @@
+// This file is part of Dirble - https://www.github.com/nccgroup/dirble
+// Copyright (C) 2019 Izzy Whistlecroft <Izzy(dot)Whistlecroft(at)nccgroup(dot)com>
+// Released as open source by NCC Group Plc - https://www.nccgroup.com/
+//
+// Dirble is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Dirble is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{arg_parse::get_version_string, request::Collector};
+use curl::{Error, easy::Easy2};
+use serde::Serialize;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    sync::mpsc,
+    time::Duration,
+};
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
+use url::Url;
+
+// Sends entries to the HAR thread, if a HAR file is being written. None
+// tells the thread to close off the archive.
+pub type HarSender = Option<mpsc::Sender<Option<Entry>>>;
+
+// Streams entries into an HTTP Archive as requests are made, so that
+// bodies don't have to be held in memory for the whole scan
+pub struct HarWriter {
+    writer: BufWriter<File>,
+    entries: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Entry {
+    started_date_time: String,
+    time: f64,
+    request: Request,
+    response: Response,
+    cache: Cache,
+    timings: Timings,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Request {
+    method: String,
+    url: String,
+    http_version: String,
+    cookies: Vec<NameValue>,
+    headers: Vec<NameValue>,
+    query_string: Vec<NameValue>,
+    headers_size: i64,
+    body_size: i64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Response {
+    status: u32,
+    status_text: String,
+    http_version: String,
+    cookies: Vec<NameValue>,
+    headers: Vec<NameValue>,
+    content: Content,
+    #[serde(rename = "redirectURL")]
+    redirect_url: String,
+    headers_size: i64,
+    body_size: i64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Content {
+    size: i64,
+    mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Cache {}
+
+// Durations of each phase of the request in milliseconds, -1 where the
+// phase didn't apply
+#[derive(Serialize)]
+struct Timings {
+    blocked: f64,
+    dns: f64,
+    connect: f64,
+    ssl: f64,
+    send: f64,
+    wait: f64,
+    receive: f64,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct NameValue {
+    name: String,
+    value: String,
+}
+
+// Writes the entries sent by the threads making requests until told to
+// stop, then closes off the archive. Entries sent after that are dropped
+// so that the file is still valid when the scan is interrupted.
+pub fn har_thread(
+    rx: mpsc::Receiver<Option<Entry>>,
+    mut har_writer: HarWriter,
+) {
+    while let Ok(Some(entry)) = rx.recv() {
+        har_writer.record(&entry);
+    }
+    har_writer.finish();
+}
+
+impl HarWriter {
+    pub fn create(path: &Path) -> Self {
+        let file = match File::create(path) {
+            Err(why) => panic!("couldn't create {}: {}", path.display(), why),
+            Ok(file) => file,
+        };
+        let mut writer = BufWriter::new(file);
+
+        // Everything up to the start of the entries array is written now,
+        // the closing brackets are written by finish
+        write!(
+            writer,
+            "{{\"log\":{{\"version\":\"1.2\",\"creator\":{{\"name\":\"dirble\",\
+             \"version\":{}}},\"entries\":[",
+            serde_json::to_string(get_version_string()).unwrap()
+        )
+        .unwrap();
+
+        HarWriter { writer, entries: 0 }
+    }
+
+    // Appends an entry to the archive
+    pub fn record(&mut self, entry: &Entry) {
+        if self.entries > 0 {
+            self.writer.write_all(b",").unwrap();
+        }
+        serde_json::to_writer(&mut self.writer, entry).unwrap();
+        self.entries += 1;
+    }
+
+    // Closes off the archive, no more entries can be written after this
+    pub fn finish(mut self) {
+        self.writer.write_all(b"]}}\n").unwrap();
+        self.writer.flush().unwrap();
+    }
+}
+
+impl Entry {
+    // Creates an entry for the request that was last made by the easy
+    pub fn from_easy(
+        easy: &mut Easy2<Collector>,
+        target: &str,
+        error: Option<&Error>,
+        max_body_size: Option<usize>,
+    ) -> Self {
+        let total = millis(easy.total_time());
+        let started = OffsetDateTime::now_utc()
+            - Duration::from_secs_f64(total.max(0.0) / 1000.0);
+
+        let timings = Timings::from_easy(easy, total);
+        let status = easy.response_code().unwrap_or(0);
+        let mime_type = easy
+            .content_type()
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+            .to_string();
+        let redirect_url = easy
+            .redirect_url()
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+            .to_string();
+        let body_size = easy.download_size().map_or(-1, |size| size as i64);
+        let declared_size = easy.content_length_download().unwrap_or(-1.0);
+
+        let collector = easy.get_ref();
+
+        // Fall back to the configured verb if no request was sent
+        let (method, http_version, request_headers) =
+            parse_request_headers(collector.request_headers());
+        let method = method.unwrap_or_else(|| {
+            format!("{:?}", collector.http_verb()).to_uppercase()
+        });
+        let (status_text, response_version, response_headers) =
+            parse_response_headers(collector.response_headers());
+
+        let contents = collector.contents();
+        let size = if collector.is_truncated() && declared_size >= 0.0 {
+            declared_size as i64
+        } else {
+            contents.len() as i64
+        };
+
+        let (text, content_comment) = match max_body_size {
+            Some(0) => (None, None),
+            Some(max_body_size) if contents.len() > max_body_size => (
+                Some(String::from_utf8_lossy(&contents[..max_body_size])),
+                Some("truncated"),
+            ),
+            _ if collector.is_truncated() => {
+                (Some(String::from_utf8_lossy(contents)), Some("truncated"))
+            }
+            _ => (Some(String::from_utf8_lossy(contents)), None),
+        };
+
+        let query_string = Url::parse(target)
+            .map(|url| {
+                url.query_pairs()
+                    .map(|(name, value)| NameValue {
+                        name: name.into_owned(),
+                        value: value.into_owned(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Entry {
+            started_date_time: started.format(&Rfc3339).unwrap(),
+            time: total.max(0.0),
+            request: Request {
+                method,
+                url: target.into(),
+                http_version: http_version.unwrap_or_default(),
+                cookies: Vec::new(),
+                headers: request_headers,
+                query_string,
+                headers_size: collector.request_headers().len() as i64,
+                body_size: 0,
+            },
+            response: Response {
+                status,
+                status_text,
+                http_version: response_version,
+                cookies: Vec::new(),
+                headers: response_headers,
+                content: Content {
+                    size,
+                    mime_type,
+                    text: text.map(|text| text.into_owned()),
+                    comment: content_comment.map(String::from),
+                },
+                redirect_url,
+                headers_size: -1,
+                body_size,
+            },
+            cache: Cache {},
+            timings,
+            comment: error.map(|error| error.to_string()),
+        }
+    }
+}
+
+impl Timings {
+    // curl gives the time from the start of the request to the end of
+    // each phase, so each HAR timing is the gap between two of those
+    fn from_easy(easy: &mut Easy2<Collector>, total: f64) -> Self {
+        let dns = millis(easy.namelookup_time());
+        let connect = millis(easy.connect_time());
+        let app_connect = millis(easy.appconnect_time());
+        let pre_transfer = millis(easy.pretransfer_time());
+        let start_transfer = millis(easy.starttransfer_time());
+
+        // The connect timing includes the TLS handshake
+        let connected = connect.max(app_connect);
+
+        Timings {
+            blocked: -1.0,
+            dns,
+            connect: (connected - dns).max(0.0),
+            ssl: if app_connect > 0.0 {
+                (app_connect - connect).max(0.0)
+            } else {
+                -1.0
+            },
+            send: (pre_transfer - connected).max(0.0),
+            wait: (start_transfer - pre_transfer).max(0.0),
+            receive: (total - start_transfer).max(0.0),
+        }
+    }
+}
+
+// Convert a timing from curl into milliseconds, -1 if it isn't available
+#[inline]
+fn millis(duration: Result<Duration, Error>) -> f64 {
+    duration.map_or(-1.0, |duration| duration.as_secs_f64() * 1000.0)
+}
+
+// Splits the outgoing header block into the method, HTTP version and
+// headers
+fn parse_request_headers(
+    headers: &[u8],
+) -> (Option<String>, Option<String>, Vec<NameValue>) {
+    let headers = String::from_utf8_lossy(headers);
+    let mut lines = headers.lines();
+
+    let Some(request_line) = lines.next() else {
+        return (None, None, Vec::new());
+    };
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().map(String::from);
+    let http_version = parts.last().map(String::from);
+
+    (
+        method,
+        http_version,
+        lines.filter_map(parse_header).collect(),
+    )
+}
+
+// Takes the status line and header lines of the response and returns the
+// status text, HTTP version and headers
+fn parse_response_headers(
+    headers: &[String],
+) -> (String, String, Vec<NameValue>) {
+    let Some((status_line, headers)) = headers.split_first() else {
+        return (String::new(), String::new(), Vec::new());
+    };
+
+    // The status line is of the form "HTTP/1.1 200 OK", with the text
+    // being optional
+    let mut parts = status_line.trim_end().splitn(3, ' ');
+    let http_version = parts.next().unwrap_or_default().to_string();
+    let status_text = parts.nth(1).unwrap_or_default().to_string();
+
+    (
+        status_text,
+        http_version,
+        headers
+            .iter()
+            .filter_map(|line| parse_header(line))
+            .collect(),
+    )
+}
+
+fn parse_header(line: &str) -> Option<NameValue> {
+    let (name, value) = line.split_once(':')?;
+    Some(NameValue {
+        name: name.trim().into(),
+        value: value.trim().into(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{arg_parse::GlobalOpts, request};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_parse_headers() {
+        let (method, http_version, headers) = parse_request_headers(
+            b"GET /ok HTTP/1.1\r\nHost: localhost\r\nAccept: */*\r\n\r\n",
+        );
+        assert_eq!(method.as_deref(), Some("GET"));
+        assert_eq!(http_version.as_deref(), Some("HTTP/1.1"));
+        assert_eq!(
+            headers,
+            vec![
+                NameValue {
+                    name: "Host".into(),
+                    value: "localhost".into(),
+                },
+                NameValue {
+                    name: "Accept".into(),
+                    value: "*/*".into(),
+                },
+            ]
+        );
+
+        let (status_text, http_version, headers) = parse_response_headers(&[
+            "HTTP/1.1 404 Not Found\r\n".into(),
+            "content-length: 0\r\n".into(),
+            "\r\n".into(),
+        ]);
+        assert_eq!(status_text, "Not Found");
+        assert_eq!(http_version, "HTTP/1.1");
+        assert_eq!(
+            headers,
+            vec![NameValue {
+                name: "content-length".into(),
+                value: "0".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_record() {
+        let port = crate::test_server::launch();
+        let har_file = tempfile::NamedTempFile::new().unwrap();
+        let (har_tx, har_rx) = mpsc::channel();
+        let har_writer = HarWriter::create(har_file.path());
+        let har_thread = std::thread::spawn(|| har_thread(har_rx, har_writer));
+
+        let global_opts = Arc::new(GlobalOpts {
+            har_file: Some(har_file.path().display().to_string()),
+            har_body_size: Some(4),
+            ..Default::default()
+        });
+        let mut easy =
+            request::generate_easy(&global_opts, &Some(har_tx.clone()));
+        for path in ["ok", "notfound?a=b"] {
+            let target = format!("http://localhost:{port}/{path}");
+            request::make_request(&mut easy, target.parse().unwrap());
+        }
+
+        // Anything requested after the archive is finished isn't recorded
+        har_tx.send(None).unwrap();
+        har_thread.join().unwrap();
+        request::make_request(
+            &mut easy,
+            format!("http://localhost:{port}/ok").parse().unwrap(),
+        );
+
+        let har: serde_json::Value =
+            serde_json::from_reader(File::open(har_file.path()).unwrap())
+                .unwrap();
+        let entries = har["log"]["entries"].as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+
+        let ok = &entries[0];
+        assert_eq!(ok["request"]["method"], "GET");
+        assert_eq!(ok["response"]["status"], 200);
+        assert_eq!(ok["response"]["content"]["size"], 10);
+        assert_eq!(
+            ok["response"]["content"]["text"].as_str().unwrap().len(),
+            4
+        );
+        assert_eq!(ok["response"]["content"]["comment"], "truncated");
+        assert!(
+            ok["request"]["headers"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|header| header["name"] == "Host")
+        );
+
+        let not_found = &entries[1];
+        assert_eq!(not_found["response"]["status"], 404);
+        assert_eq!(not_found["request"]["queryString"][0]["name"], "a");
+        assert_eq!(not_found["request"]["queryString"][0]["value"], "b");
+    }
+}
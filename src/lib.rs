@@ -19,11 +19,11 @@ use crate::arg_parse::GlobalOpts;
 use log::{LevelFilter, debug, error, info, warn};
 use simplelog::{ColorChoice, TermLogger, TerminalMode};
 use std::{
-    collections::VecDeque,
+    collections::{HashSet, VecDeque},
     env::current_exe,
     path::Path,
     sync::{
-        Arc,
+        Arc, Mutex,
         atomic::{AtomicBool, Ordering},
         mpsc::{self, Receiver, Sender},
     },
@@ -34,13 +34,20 @@ use url::Url;
 
 #[macro_use]
 pub mod arg_parse;
+mod bypass;
 mod content_parse;
+mod drift;
+mod har;
+mod listing;
 mod output;
 mod output_format;
 mod output_thread;
 mod request;
 mod request_thread;
+mod seed;
+mod spider;
 mod validator_thread;
+mod verify;
 mod wordlist;
 
 #[cfg(test)]
@@ -71,6 +78,27 @@ pub fn dirble_main(args: GlobalOpts) {
     )
     .expect("Failed to init TermLogger");
 
+    // Start recording all requests if a HAR file was asked for. The
+    // writer is owned by its own thread which the requests are sent to.
+    let (har_tx, har_thread) = match &global_opts.har_file {
+        Some(har_file) => {
+            let (har_tx, har_rx) = mpsc::channel();
+            let har_writer = har::HarWriter::create(Path::new(har_file));
+            let har_thread =
+                thread::spawn(|| har::har_thread(har_rx, har_writer));
+            (Some(har_tx), Some(har_thread))
+        }
+        None => (None, None),
+    };
+
+    // Verifying a previous report doesn't need a wordlist or any of the
+    // scanning threads
+    if let Some(verify_report) = &global_opts.verify_report {
+        verify::verify_main(global_opts.clone(), verify_report, &har_tx);
+        finish_har(har_tx, har_thread);
+        return;
+    }
+
     // Get the wordlist file from the arguments. If it has not been set
     // then try the default wordlist locations.
     let mut wordlist: Vec<String> = Vec::new();
@@ -141,6 +169,14 @@ pub fn dirble_main(args: GlobalOpts) {
 
     let wordlist = Arc::new(wordlist);
 
+    // Directories on case insensitive servers are scanned with a copy of
+    // the wordlist that has words only differing by case removed
+    let case_insensitive_wordlist = if global_opts.detect_case {
+        Arc::new(wordlist::dedup_case_insensitive(&wordlist))
+    } else {
+        wordlist.clone()
+    };
+
     // Create a channel for threads to communicate with the parent on
     // This is used to send information about ending threads and
     // information on responses
@@ -156,12 +192,20 @@ pub fn dirble_main(args: GlobalOpts) {
         Sender<Option<validator_thread::DirectoryInfo>>,
         Receiver<Option<validator_thread::DirectoryInfo>>,
     ) = mpsc::channel();
+    // The not found responses of each directory are sent to the output
+    // thread to be included in the reports
+    let (baseline_tx, baseline_rx): (
+        Sender<output::DirectoryBaseline>,
+        Receiver<output::DirectoryBaseline>,
+    ) = mpsc::channel();
 
     let validator_global_opts = global_opts.clone();
+    let validator_har_tx = har_tx.clone();
     let validator_thread = thread::spawn(|| {
         validator_thread::validator_thread(
             to_validate_rx,
             to_scan_tx,
+            validator_har_tx,
             validator_global_opts,
         )
     });
@@ -178,48 +222,54 @@ pub fn dirble_main(args: GlobalOpts) {
     // Create a queue for URIs that need to be scanned
     let mut scan_queue: VecDeque<wordlist::UriGenerator> = VecDeque::new();
 
+    // Each host has its own wordlists so that the words found in its
+    // robots.txt and sitemaps are only used on that host
+    let mut host_wordlists: Vec<_> = global_opts
+        .hostnames
+        .iter()
+        .map(|_| (wordlist.clone(), case_insensitive_wordlist.clone()))
+        .collect();
+
+    // Define the max number of threads and the number of threads
+    // currently in use
+    let mut threads_in_use = 0;
+
+    let mut seeding_hosts = Vec::new();
+
     // Push the host URI to the scan queue
     for _i in 0..global_opts.hostnames.len() {
         let response = to_scan_rx.recv().unwrap();
 
-        match response {
-            None => continue,
-            Some(dir_info) => {
-                match &dir_info.validator {
-                    Some(validator) => {
-                        if validator.scan_folder(&global_opts.scan_opts) {
-                            add_dir_to_scan_queue(
-                                &mut scan_queue,
-                                &global_opts,
-                                &dir_info,
-                                &wordlist,
-                                true,
-                            );
-                        } else {
-                            info!(
-                                "Skipping {}{}",
-                                dir_info.url,
-                                &validator.print_alert()
-                            )
-                        }
-                    }
-                    // If there is no validator, then scan the folder
-                    None => {
-                        add_dir_to_scan_queue(
-                            &mut scan_queue,
-                            &global_opts,
-                            &dir_info,
-                            &wordlist,
-                            true,
-                        );
-                    }
-                }
-            }
+        let Some(dir_info) = response else {
+            continue;
+        };
+        baseline_tx
+            .send(dir_info.baseline(&global_opts.scan_opts))
+            .unwrap();
+        if let Some(validator) = &dir_info.validator
+            && !validator.scan_folder(&global_opts.scan_opts)
+        {
+            info!("Skipping {}{}", dir_info.url, &validator.print_alert());
+            continue;
         }
+
+        // Hosts being seeded are scanned once their seeds are known
+        if global_opts.robots_sitemap {
+            seeding_hosts.push(dir_info);
+            continue;
+        }
+
+        let (wordlist, case_insensitive_wordlist) =
+            &host_wordlists[dir_info.parent_index];
+        add_dir_to_scan_queue(
+            &mut scan_queue,
+            &global_opts,
+            &dir_info,
+            wordlist,
+            case_insensitive_wordlist,
+            true,
+        );
     }
-    // Define the max number of threads and the number of threads
-    // currently in use
-    let mut threads_in_use = 0;
 
     let file_handles = output::create_files(global_opts.clone());
     let output_global_opts = global_opts.clone();
@@ -227,11 +277,16 @@ pub fn dirble_main(args: GlobalOpts) {
     let output_thread = thread::spawn(|| {
         output_thread::output_thread(
             output_rx,
+            baseline_rx,
             output_global_opts,
             file_handles,
         )
     });
 
+    // Urls which have been spidered, so that pages linking to each other
+    // are only requested once
+    let spidered_urls = Arc::new(Mutex::new(HashSet::new()));
+
     let caught_ctrl_c = Arc::new(AtomicBool::new(false));
     let caught_ctrl_c_clone_for_handler = caught_ctrl_c.clone();
     ctrlc::set_handler(move || {
@@ -240,10 +295,58 @@ pub fn dirble_main(args: GlobalOpts) {
     })
     .expect("Unable to attach interrupt signal handler");
 
+    // Each host is seeded from its robots.txt and sitemaps in its own
+    // thread, which sends it back with the words that were found
+    let (seeded_tx, seeded_rx) = mpsc::channel();
+    for dir_info in seeding_hosts {
+        let seeded_tx = seeded_tx.clone();
+        let to_validate_tx_clone = mpsc::Sender::clone(&to_validate_tx);
+        let output_tx_clone = mpsc::Sender::clone(&output_tx);
+        let har_tx_clone = har_tx.clone();
+        let arg_clone = global_opts.clone();
+        thread::spawn(|| {
+            seed::seed_thread(
+                dir_info,
+                seeded_tx,
+                to_validate_tx_clone,
+                output_tx_clone,
+                har_tx_clone,
+                arg_clone,
+            )
+        });
+        threads_in_use += 1;
+    }
+
     // Loop of checking for messages from the threads,
     // spawning new threads on items in the scan queue
     // and checking if the program is done
     while !caught_ctrl_c.load(Ordering::SeqCst) {
+        // Hosts which have been seeded are scanned with the words from
+        // their seeds. This is checked first so that the wordlist is
+        // updated before any of the seeded directories are scanned.
+        if let Ok((dir_info, words)) = seeded_rx.try_recv() {
+            let mut seeded_wordlist = wordlist.to_vec();
+            seeded_wordlist.extend(words);
+            seeded_wordlist.sort();
+            seeded_wordlist.dedup();
+            let seeded_wordlist = Arc::new(seeded_wordlist);
+            let seeded_case_insensitive_wordlist = if global_opts.detect_case {
+                Arc::new(wordlist::dedup_case_insensitive(&seeded_wordlist))
+            } else {
+                seeded_wordlist.clone()
+            };
+            add_dir_to_scan_queue(
+                &mut scan_queue,
+                &global_opts,
+                &dir_info,
+                &seeded_wordlist,
+                &seeded_case_insensitive_wordlist,
+                true,
+            );
+            host_wordlists[dir_info.parent_index] =
+                (seeded_wordlist, seeded_case_insensitive_wordlist);
+        }
+
         // Check for messages from the threads
         let to_scan = to_scan_rx.try_recv();
 
@@ -258,6 +361,11 @@ pub fn dirble_main(args: GlobalOpts) {
             // Check the validator to see if the directory should
             // be scanned
             else {
+                baseline_tx
+                    .send(dir_info.baseline(&global_opts.scan_opts))
+                    .unwrap();
+                let (wordlist, case_insensitive_wordlist) =
+                    &host_wordlists[dir_info.parent_index];
                 match &dir_info.validator {
                     Some(validator) => {
                         if validator.scan_folder(&global_opts.scan_opts) {
@@ -265,7 +373,8 @@ pub fn dirble_main(args: GlobalOpts) {
                                 &mut scan_queue,
                                 &global_opts,
                                 &dir_info,
-                                &wordlist,
+                                wordlist,
+                                case_insensitive_wordlist,
                                 false,
                             );
                         } else {
@@ -282,7 +391,8 @@ pub fn dirble_main(args: GlobalOpts) {
                             &mut scan_queue,
                             &global_opts,
                             &dir_info,
-                            &wordlist,
+                            wordlist,
+                            case_insensitive_wordlist,
                             false,
                         );
                     }
@@ -297,6 +407,8 @@ pub fn dirble_main(args: GlobalOpts) {
             // reference, then pop the scan target from the queue
             let to_validate_tx_clone = mpsc::Sender::clone(&to_validate_tx);
             let output_tx_clone = mpsc::Sender::clone(&output_tx);
+            let har_tx_clone = har_tx.clone();
+            let spidered_urls_clone = spidered_urls.clone();
             let list_gen = scan_queue.pop_front().unwrap();
             let arg_clone = global_opts.clone();
 
@@ -306,6 +418,8 @@ pub fn dirble_main(args: GlobalOpts) {
                 request_thread::thread_spawn(
                     to_validate_tx_clone,
                     output_tx_clone,
+                    har_tx_clone,
+                    spidered_urls_clone,
                     list_gen,
                     arg_clone,
                 )
@@ -323,19 +437,39 @@ pub fn dirble_main(args: GlobalOpts) {
         thread::sleep(Duration::from_millis(1));
     }
 
+    // Close off the HAR file first, as request threads may still be
+    // running if the scan was interrupted
+    finish_har(har_tx, har_thread);
+
     // loop to check that report printing has ended
+    // Closing the baseline channel lets the output thread know that it
+    // has all of them
+    drop(baseline_tx);
     output_tx.send(generate_end()).unwrap();
     to_validate_tx.send(generate_end()).unwrap();
     output_thread.join().unwrap();
     validator_thread.join().unwrap();
 }
 
+// Tells the HAR thread to close off the archive and waits for it to be
+// written
+fn finish_har(
+    har_tx: har::HarSender,
+    har_thread: Option<thread::JoinHandle<()>>,
+) {
+    if let (Some(har_tx), Some(har_thread)) = (har_tx, har_thread) {
+        har_tx.send(None).unwrap();
+        har_thread.join().unwrap();
+    }
+}
+
 #[inline]
 fn add_dir_to_scan_queue(
     scan_queue: &mut VecDeque<wordlist::UriGenerator>,
     global_opts: &Arc<arg_parse::GlobalOpts>,
     dir_info: &validator_thread::DirectoryInfo,
     wordlist: &Arc<Vec<String>>,
+    case_insensitive_wordlist: &Arc<Vec<String>>,
     first_run: bool,
 ) {
     // first_run is true when the initial scans are being initialised
@@ -358,10 +492,37 @@ fn add_dir_to_scan_queue(
         wordlist_split = global_opts.wordlist_split;
     }
 
+    let wordlist = if dir_info.case_insensitive {
+        case_insensitive_wordlist
+    } else {
+        wordlist
+    };
+
+    let mut queued_modifiers = HashSet::new();
     for prefix in &global_opts.prefixes {
         for extension in &global_opts.extensions {
+            // Prefixes and extensions which only differ by case are
+            // equivalent in case insensitive directories
+            if dir_info.case_insensitive
+                && !queued_modifiers
+                    .insert((prefix.to_lowercase(), extension.to_lowercase()))
+            {
+                continue;
+            }
+
+            // Use the validator for this prefix and extension if there is
+            // one, otherwise the directory's
+            let validator = dir_info
+                .suffix_validators
+                .get(&(prefix.clone(), extension.clone()))
+                .or(dir_info.validator.as_ref());
+
+            // All of the generators for the prefix and extension stop if
+            // one finds that they're always found in the directory
+            let wildcard = Arc::new(AtomicBool::new(false));
+
             for start_index in 0..wordlist_split {
-                scan_queue.push_back(wordlist::UriGenerator::new(
+                let mut uri_gen = wordlist::UriGenerator::new(
                     dir_info.url.clone(),
                     prefix.clone(),
                     extension.clone(),
@@ -370,45 +531,77 @@ fn add_dir_to_scan_queue(
                     wordlist_split,
                     dir_info.parent_index,
                     dir_info.parent_depth,
-                    dir_info.validator.clone(),
+                    validator.cloned(),
                     global_opts.extension_substitution,
-                ));
+                    global_opts.path_mode,
+                    global_opts.add_slash,
+                );
+                uri_gen.wildcard = wildcard.clone();
+                scan_queue.push_back(uri_gen);
             }
         }
     }
 }
 
 fn generate_end() -> request::RequestResponse {
-    request::RequestResponse {
-        url: Url::parse("data:MAIN ENDING").unwrap(),
-        code: 0,
-        content_len: 0,
-        is_directory: false,
-        is_listable: false,
-        redirect_url: String::from(""),
-        found_from_listable: false,
-        parent_index: 0,
-        parent_depth: 0,
-    }
+    request::RequestResponse::new(Url::parse("data:MAIN ENDING").unwrap())
 }
 
 #[cfg(test)]
 mod test {
-    use crate::request::RequestResponse;
+    use crate::{
+        add_dir_to_scan_queue, arg_parse::GlobalOpts, request::RequestResponse,
+        validator_thread::DirectoryInfo,
+    };
+    use std::{collections::VecDeque, sync::Arc};
     use url::Url;
 
     impl Default for RequestResponse {
         fn default() -> Self {
             RequestResponse {
-                url: Url::parse("http://example.com/").unwrap(),
                 code: 200,
                 content_len: 200,
-                is_directory: false,
-                is_listable: false,
-                redirect_url: "".into(),
-                found_from_listable: false,
-                parent_index: 0,
-                parent_depth: 0,
+                ..RequestResponse::new(
+                    Url::parse("http://example.com/").unwrap(),
+                )
+            }
+        }
+    }
+
+    #[test]
+    fn test_wildcard_per_suffix() {
+        let global_opts = Arc::new(GlobalOpts {
+            extensions: vec![String::new(), ".php".into()],
+            wordlist_split: 2,
+            ..Default::default()
+        });
+        let dir_info = DirectoryInfo::new(
+            Url::parse("http://example.com/").unwrap(),
+            None,
+            0,
+            0,
+        );
+        let wordlist = Arc::new(vec!["word".into()]);
+        let mut scan_queue = VecDeque::new();
+        add_dir_to_scan_queue(
+            &mut scan_queue,
+            &global_opts,
+            &dir_info,
+            &wordlist,
+            &wordlist,
+            false,
+        );
+
+        // Generators only share a wildcard flag with the others for the
+        // same prefix and extension
+        let generators: Vec<_> = scan_queue.into_iter().collect();
+        assert_eq!(generators.len(), 4);
+        for a in &generators {
+            for b in &generators {
+                assert_eq!(
+                    Arc::ptr_eq(&a.wildcard, &b.wildcard),
+                    a.suffix == b.suffix
+                );
             }
         }
     }
@@ -0,0 +1,721 @@
+// This file is part of Dirble - https://www.github.com/nccgroup/dirble
+// Copyright (C) 2019 Izzy Whistlecroft <Izzy(dot)Whistlecroft(at)nccgroup(dot)com>
+// Released as open source by NCC Group Plc - https://www.nccgroup.com/
+//
+// Dirble is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Dirble is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
+
+use regex::Regex;
+use select::{
+    document::Document,
+    predicate::{Attr, Class, Descendant, Name, Predicate},
+};
+use std::sync::LazyLock;
+use time::{
+    PrimitiveDateTime, format_description::BorrowedFormatItem,
+    macros::format_description,
+};
+use url::Url;
+
+// Matches the date and size or <dir> before each entry of an IIS listing
+static IIS_ROW_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)\d{1,2}/\d{1,2}/\d{4}\s+\d{1,2}:\d{2}\s+[AP]M\s+(<dir>|\d+)",
+    )
+    .unwrap()
+});
+
+// The formats of the modification times shown by each style of listing,
+// along with a regex to find them in the text of a row
+static DATE_FORMATS: LazyLock<Vec<(Regex, &[BorrowedFormatItem])>> =
+    LazyLock::new(|| {
+        [
+            // Apache
+            (
+                r"\d{4}-\d{2}-\d{2} \d{2}:\d{2}",
+                format_description!("[year]-[month]-[day] [hour]:[minute]"),
+            ),
+            // Caddy
+            (
+                r"\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}",
+                format_description!(
+                    "[year]-[month]-[day]T[hour]:[minute]:[second]"
+                ),
+            ),
+            // nginx
+            (
+                r"\d{2}-[A-Z][a-z]{2}-\d{4} \d{2}:\d{2}",
+                format_description!(
+                    "[day]-[month repr:short]-[year] [hour]:[minute]"
+                ),
+            ),
+            // lighttpd
+            (
+                r"\d{4}-[A-Z][a-z]{2}-\d{2} \d{2}:\d{2}:\d{2}",
+                format_description!(
+                    "[year]-[month repr:short]-[day] [hour]:[minute]:[second]"
+                ),
+            ),
+            // IIS
+            (
+                r"\d{1,2}/\d{1,2}/\d{4} \d{1,2}:\d{2} [AP]M",
+                format_description!(
+                    "[month padding:none]/[day padding:none]/[year] [hour repr:12 padding:none]:[minute] [period]"
+                ),
+            ),
+            // Jetty
+            (
+                r"[A-Z][a-z]{2} \d{1,2}, \d{4}, \d{1,2}:\d{2}:\d{2} [AP]M",
+                format_description!(
+                    "[month repr:short] [day padding:none], [year], [hour repr:12 padding:none]:[minute]:[second] [period]"
+                ),
+            ),
+            // Tomcat
+            (
+                r"\d{2} [A-Z][a-z]{2} \d{4} \d{2}:\d{2}:\d{2}",
+                format_description!(
+                    "[day] [month repr:short] [year] [hour]:[minute]:[second]"
+                ),
+            ),
+        ]
+        .into_iter()
+        .map(|(regex, format)| (Regex::new(regex).unwrap(), format))
+        .collect()
+    });
+
+// Matches sizes given in bytes, or with a unit in either powers of 1000 or
+// 1024, which are treated the same
+static SIZE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)^(\d[\d,]*(?:\.\d+)?) ?([kmgt]?)(?:i?b|bytes?)?$").unwrap()
+});
+
+struct ListingStyle {
+    name: &'static str,
+    detect: fn(&Document) -> bool,
+    rows: fn(&Document) -> Vec<Row>,
+}
+
+// The link to an entry in a listing, with the size and modification time
+// if they're shown alongside it
+#[derive(Default)]
+struct Row {
+    link: String,
+    size: Option<u64>,
+    modified: Option<String>,
+}
+
+// The directory listing styles of different servers. Each detects whether
+// a page is a listing in its style, and returns the rows for the entries
+// of the listing. They're checked in order, so styles with more specific
+// markers come before those they could be mistaken for.
+static LISTING_STYLES: [ListingStyle; 8] = [
+    ListingStyle {
+        name: "apache",
+        detect: detect_apache,
+        rows: rows_apache,
+    },
+    ListingStyle {
+        name: "nginx",
+        detect: detect_nginx,
+        rows: rows_pre,
+    },
+    ListingStyle {
+        name: "lighttpd",
+        detect: detect_lighttpd,
+        rows: rows_table,
+    },
+    ListingStyle {
+        name: "iis",
+        detect: detect_iis,
+        rows: rows_pre,
+    },
+    ListingStyle {
+        name: "caddy",
+        detect: detect_caddy,
+        rows: rows_table,
+    },
+    ListingStyle {
+        name: "jetty",
+        detect: detect_jetty,
+        rows: rows_table,
+    },
+    ListingStyle {
+        name: "tomcat",
+        detect: detect_tomcat,
+        rows: rows_table,
+    },
+    ListingStyle {
+        name: "python",
+        detect: detect_python,
+        rows: rows_list,
+    },
+];
+
+// A page which was detected as a directory listing
+#[derive(Debug, PartialEq)]
+pub struct Listing {
+    // The name of the style of the listing
+    pub style: &'static str,
+    pub entries: Vec<ListingEntry>,
+}
+
+// A file or directory in a listing. The modification time is given as
+// shown by the server, without a timezone.
+#[derive(Debug, PartialEq)]
+pub struct ListingEntry {
+    pub url: Url,
+    pub size: Option<u64>,
+    pub modified: Option<String>,
+}
+
+// Returns the listing if the content is a directory listing in one of the
+// known styles. Only entries under the directory are included, so links
+// to parent directories and for sorting the listing are left out.
+pub fn parse_listing(content: &str, dir_url: &Url) -> Option<Listing> {
+    let document = Document::from(content);
+    let style = LISTING_STYLES
+        .iter()
+        .find(|style| (style.detect)(&document))?;
+
+    let mut entries: Vec<ListingEntry> = Vec::new();
+    for row in (style.rows)(&document) {
+        let Some(url) = resolve(dir_url, &row.link) else {
+            continue;
+        };
+        if !entries.iter().any(|entry| entry.url == url) {
+            entries.push(ListingEntry {
+                url,
+                size: row.size,
+                modified: row.modified,
+            });
+        }
+    }
+
+    Some(Listing {
+        style: style.name,
+        entries,
+    })
+}
+
+// Resolves a link in a listing, returning it if it's under the directory
+fn resolve(dir_url: &Url, link: &str) -> Option<Url> {
+    let link = link.trim();
+    if link.is_empty() || link.starts_with('?') || link.starts_with('#') {
+        return None;
+    }
+
+    let mut url = dir_url.join(link).ok()?;
+    url.set_query(None);
+    url.set_fragment(None);
+    if url.as_str().len() > dir_url.as_str().len()
+        && url.as_str().starts_with(dir_url.as_str())
+    {
+        Some(url)
+    } else {
+        None
+    }
+}
+
+// The lowercased text of the first element with the given name
+fn text_of(document: &Document, name: &str) -> String {
+    document
+        .find(Name(name))
+        .next()
+        .map(|node| node.text().trim().to_lowercase())
+        .unwrap_or_default()
+}
+
+// Apache's mod_autoindex has links for sorting the columns, and a footer
+// naming the server
+fn detect_apache(document: &Document) -> bool {
+    text_of(document, "title").starts_with("index of /")
+        && (document
+            .find(Name("a"))
+            .filter_map(|node| node.attr("href"))
+            .any(|href| href.starts_with("?C="))
+            || text_of(document, "address").starts_with("apache"))
+}
+
+// Depending on the options, Apache lists entries in a table, a pre or a
+// list
+fn rows_apache(document: &Document) -> Vec<Row> {
+    let mut rows = rows_table(document);
+    rows.append(&mut rows_pre(document));
+    rows.append(&mut rows_list(document));
+    rows
+}
+
+// nginx's autoindex is a pre which always starts with a link to ../
+fn detect_nginx(document: &Document) -> bool {
+    text_of(document, "title").starts_with("index of /")
+        && document
+            .find(Descendant(Name("pre"), Name("a")))
+            .next()
+            .and_then(|node| node.attr("href"))
+            == Some("../")
+}
+
+fn detect_lighttpd(document: &Document) -> bool {
+    document
+        .find(Name("table").and(Attr("summary", "Directory Listing")))
+        .next()
+        .is_some()
+        || document
+            .find(Name("div").and(Class("foot")))
+            .any(|node| node.text().to_lowercase().starts_with("lighttpd"))
+}
+
+// IIS lists the date and the size or <dir> of each entry in a pre
+fn detect_iis(document: &Document) -> bool {
+    document.find(Name("pre")).any(|node| {
+        node.text().contains("[To Parent Directory]")
+            || IIS_ROW_REGEX.is_match(&node.text())
+    })
+}
+
+// Caddy's file server marks the rows of files, and links to Caddy in the
+// footer
+fn detect_caddy(document: &Document) -> bool {
+    document
+        .find(Descendant(Name("tr").and(Class("file")), Class("name")))
+        .next()
+        .is_some()
+        || document
+            .find(Name("a"))
+            .filter_map(|node| node.attr("href"))
+            .any(|href| href.contains("caddyserver.com"))
+}
+
+fn detect_jetty(document: &Document) -> bool {
+    text_of(document, "title").starts_with("directory: /")
+        && text_of(document, "h1").starts_with("directory: /")
+}
+
+fn detect_tomcat(document: &Document) -> bool {
+    text_of(document, "title").starts_with("directory listing for [")
+}
+
+// Python's http.server lists entries in a ul
+fn detect_python(document: &Document) -> bool {
+    text_of(document, "title").starts_with("directory listing for /")
+        && document
+            .find(Descendant(Name("li"), Name("a")))
+            .next()
+            .is_some()
+}
+
+// Each line of a pre has a link to an entry, with the modification time
+// and size either before or after it
+fn rows_pre(document: &Document) -> Vec<Row> {
+    let mut rows = Vec::new();
+
+    for pre in document.find(Name("pre")) {
+        // The text on the current line before the link, and the link
+        // with the text after it
+        let mut before = String::new();
+        let mut current: Option<(String, String)> = None;
+
+        for child in pre.children() {
+            match child.name() {
+                Some("a") => {
+                    if let Some((link, after)) = current.take() {
+                        rows.push(pre_row(link, &after));
+                    }
+                    if let Some(href) = child.attr("href") {
+                        current = Some((String::from(href), before.clone()));
+                    }
+                    before.clear();
+                }
+                Some("br") => {
+                    if let Some((link, text)) = current.take() {
+                        rows.push(pre_row(link, &text));
+                    }
+                    before.clear();
+                }
+                Some(_) => {}
+                None => {
+                    let Some(text) = child.as_text() else {
+                        continue;
+                    };
+                    let mut lines = text.split('\n');
+                    let rest_of_line = lines.next().unwrap_or_default();
+                    match &mut current {
+                        Some((_, after)) => after.push_str(rest_of_line),
+                        None => before.push_str(rest_of_line),
+                    }
+                    for line in lines {
+                        if let Some((link, text)) = current.take() {
+                            rows.push(pre_row(link, &text));
+                        }
+                        before = String::from(line);
+                    }
+                }
+            }
+        }
+        if let Some((link, text)) = current.take() {
+            rows.push(pre_row(link, &text));
+        }
+    }
+
+    rows
+}
+
+// The size is the first thing on the line other than the modification
+// time. Directories have a placeholder such as - or <dir>.
+fn pre_row(link: String, text: &str) -> Row {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let (modified, rest) = match find_modified(&text) {
+        Some((modified, start, end)) => (
+            Some(modified),
+            format!("{} {}", &text[..start], &text[end..]),
+        ),
+        None => (None, text),
+    };
+    let size = rest.split_whitespace().next().and_then(parse_size);
+
+    Row {
+        link,
+        size,
+        modified,
+    }
+}
+
+// Each row of a table has a link to an entry, with the modification time
+// and size in other cells
+fn rows_table(document: &Document) -> Vec<Row> {
+    let mut rows = Vec::new();
+
+    for tr in document.find(Name("tr")) {
+        let Some(link) = tr
+            .find(Descendant(Name("td"), Name("a")))
+            .find_map(|node| node.attr("href"))
+        else {
+            continue;
+        };
+        let mut row = Row {
+            link: String::from(link),
+            ..Default::default()
+        };
+
+        for td in tr.find(Name("td")) {
+            if td.find(Name("a")).next().is_some() {
+                continue;
+            }
+            // Caddy gives the exact size and time in attributes
+            let text = td
+                .find(Name("time"))
+                .find_map(|node| node.attr("datetime"))
+                .map(String::from)
+                .unwrap_or_else(|| td.text());
+            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+            if row.modified.is_none()
+                && let Some((modified, _, _)) = find_modified(&text)
+            {
+                row.modified = Some(modified);
+            } else if row.size.is_none() {
+                row.size = td
+                    .attr("data-order")
+                    .and_then(|size| size.parse().ok())
+                    .or_else(|| parse_size(&text));
+            }
+        }
+        rows.push(row);
+    }
+
+    rows
+}
+
+fn rows_list(document: &Document) -> Vec<Row> {
+    document
+        .find(Descendant(Name("li"), Name("a")))
+        .filter_map(|node| node.attr("href"))
+        .map(|link| Row {
+            link: String::from(link),
+            ..Default::default()
+        })
+        .collect()
+}
+
+// Returns the first modification time in the text, formatted as
+// YYYY-MM-DD HH:MM:SS, along with where it was found
+fn find_modified(text: &str) -> Option<(String, usize, usize)> {
+    DATE_FORMATS.iter().find_map(|(regex, format)| {
+        let found = regex.find(text)?;
+        let modified = PrimitiveDateTime::parse(found.as_str(), format).ok()?;
+        let modified = modified
+            .format(format_description!(
+                "[year]-[month]-[day] [hour]:[minute]:[second]"
+            ))
+            .ok()?;
+        Some((modified, found.start(), found.end()))
+    })
+}
+
+// Sizes with a unit are rounded, so may be slightly out
+fn parse_size(text: &str) -> Option<u64> {
+    let captures = SIZE_REGEX.captures(text.trim())?;
+    let number: f64 = captures[1].replace(',', "").parse().ok()?;
+    let multiplier = match captures[2].to_lowercase().as_str() {
+        "k" => 1 << 10,
+        "m" => 1 << 20,
+        "g" => 1 << 30,
+        "t" => 1 << 40,
+        _ => 1u64,
+    };
+    Some((number * multiplier as f64) as u64)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const APACHE: &str = r#"<!DOCTYPE HTML PUBLIC "-//W3C//DTD HTML 3.2 Final//EN">
+<html>
+ <head>
+  <title>Index of /files</title>
+ </head>
+ <body>
+<h1>Index of /files</h1>
+  <table>
+   <tr><th valign="top"><img src="/icons/blank.gif" alt="[ICO]"></th><th><a href="?C=N;O=D">Name</a></th><th><a href="?C=M;O=A">Last modified</a></th><th><a href="?C=S;O=A">Size</a></th><th><a href="?C=D;O=A">Description</a></th></tr>
+   <tr><th colspan="5"><hr></th></tr>
+<tr><td valign="top"><img src="/icons/back.gif" alt="[PARENTDIR]"></td><td><a href="/">Parent Directory</a></td><td>&nbsp;</td><td align="right">  - </td><td>&nbsp;</td></tr>
+<tr><td valign="top"><img src="/icons/folder.gif" alt="[DIR]"></td><td><a href="backup/">backup/</a></td><td align="right">2026-10-01 09:15  </td><td align="right">  - </td><td>&nbsp;</td></tr>
+<tr><td valign="top"><img src="/icons/compressed.gif" alt="[   ]"></td><td><a href="site.tar.gz">site.tar.gz</a></td><td align="right">2026-10-17 22:03  </td><td align="right">4.2M</td><td>&nbsp;</td></tr>
+   <tr><th colspan="5"><hr></th></tr>
+</table>
+<address>Apache/2.4.62 (Debian) Server at example.com Port 80</address>
+</body></html>"#;
+
+    const APACHE_PRE: &str = r#"<html><head><title>Index of /files</title></head><body>
+<h1>Index of /files</h1>
+<pre><img src="/icons/blank.gif" alt="Icon "> <a href="?C=N;O=D">Name</a>                    <a href="?C=M;O=A">Last modified</a>      <a href="?C=S;O=A">Size</a>  <a href="?C=D;O=A">Description</a><hr><img src="/icons/back.gif" alt="[PARENTDIR]"> <a href="/">Parent Directory</a>                             -
+<img src="/icons/folder.gif" alt="[DIR]"> <a href="backup/">backup/</a>                 2026-10-01 09:15    -
+<img src="/icons/compressed.gif" alt="[   ]"> <a href="site.tar.gz">site.tar.gz</a>             2026-10-17 22:03  4.2M
+<hr></pre>
+</body></html>"#;
+
+    const NGINX: &str = r#"<html>
+<head><title>Index of /files/</title></head>
+<body>
+<h1>Index of /files/</h1><hr><pre><a href="../">../</a>
+<a href="backup/">backup/</a>                                            01-Oct-2026 09:15                   -
+<a href="site.tar.gz">site.tar.gz</a>                                        17-Oct-2026 22:03             4404019
+</pre><hr></body>
+</html>"#;
+
+    const LIGHTTPD: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<title>Index of /files/</title>
+</head>
+<body>
+<h2>Index of /files/</h2>
+<div class="list">
+<table summary="Directory Listing" cellpadding="0" cellspacing="0">
+<thead><tr><th class="n">Name</th><th class="m">Last Modified</th><th class="s">Size</th><th class="t">Type</th></tr></thead>
+<tbody>
+<tr class="d"><td class="n"><a href="../">Parent Directory</a>/</td><td class="m">&nbsp;</td><td class="s">- &nbsp;</td><td class="t">Directory</td></tr>
+<tr class="d"><td class="n"><a href="backup/">backup</a>/</td><td class="m">2026-Oct-01 09:15:00</td><td class="s">- &nbsp;</td><td class="t">Directory</td></tr>
+<tr><td class="n"><a href="site.tar.gz">site.tar.gz</a></td><td class="m">2026-Oct-17 22:03:41</td><td class="s">4.2M</td><td class="t">application/x-gzip</td></tr>
+</tbody>
+</table>
+</div>
+<div class="foot">lighttpd/1.4.76</div>
+</body>
+</html>"#;
+
+    const IIS: &str = r#"<html><head><title>example.com - /files/</title></head><body><H1>example.com - /files/</H1><hr>
+
+<pre><A HREF="/">[To Parent Directory]</A><br><br> 10/1/2026  9:15 AM        &lt;dir&gt; <A HREF="/files/backup/">backup</A><br>10/17/2026 10:03 PM      4404019 <A HREF="/files/site.tar.gz">site.tar.gz</A><br></pre><hr></body></html>"#;
+
+    const CADDY: &str = r#"<!DOCTYPE html>
+<html>
+<head><title>/files/</title></head>
+<body>
+<main>
+<div class="listing">
+<table aria-describedby="summary">
+<thead><tr><th></th><th><a href="?sort=name&order=desc">Name</a></th><th><a href="?sort=size&order=asc">Size</a></th><th><a href="?sort=time&order=asc">Modified</a></th></tr></thead>
+<tbody>
+<tr><td></td><td><a href=".."><span class="go-up">Up</span></a></td><td>&mdash;</td><td>&mdash;</td></tr>
+<tr class="file"><td></td><td><a href="./backup/"><span class="name">backup</span></a></td><td data-order="-1">&mdash;</td><td class="timestamp"><time datetime="2026-10-01T09:15:00Z">10/01/2026 09:15:00 AM +00:00</time></td></tr>
+<tr class="file"><td></td><td><a href="./site.tar.gz"><span class="name">site.tar.gz</span></a></td><td data-order="4404019" class="size">4.2 MiB</td><td class="timestamp"><time datetime="2026-10-17T22:03:41Z">10/17/2026 10:03:41 PM +00:00</time></td></tr>
+</tbody>
+</table>
+</div>
+</main>
+<footer>Served with <a rel="noopener noreferrer" href="https://caddyserver.com">Caddy</a></footer>
+</body>
+</html>"#;
+
+    const JETTY: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head><title>Directory: /files/</title></head>
+<body>
+<h1 class="title">Directory: /files/</h1>
+<table class="listing">
+<thead>
+<tr><th class="name"><a href="?C=N&amp;O=D">Name &nbsp; &#8679;</a></th><th class="lastmodified"><a href="?C=M&amp;O=A">Last Modified</a></th><th class="size"><a href="?C=S&amp;O=A">Size</a></th></tr>
+</thead>
+<tbody>
+<tr><td class="name"><a href="/files/../">Parent Directory</a></td><td class="lastmodified">-</td><td class="size">-</td></tr>
+<tr><td class="name"><a href="/files/backup/">backup/&nbsp;</a></td><td class="lastmodified">Oct 1, 2026, 9:15:00 AM UTC</td><td class="size">4,096 bytes&nbsp;</td></tr>
+<tr><td class="name"><a href="/files/site.tar.gz">site.tar.gz&nbsp;</a></td><td class="lastmodified">Oct 17, 2026, 10:03:41 PM UTC</td><td class="size">4,404,019 bytes&nbsp;</td></tr>
+</tbody>
+</table>
+</body></html>"#;
+
+    const TOMCAT: &str = r##"<!doctype html><html lang="en"><head><title>Directory Listing For [/files/]</title></head>
+<body><h1>Directory Listing For [/files/] - <a href="/"><b>Up To [/]</b></a></h1><hr class="line">
+<table width="100%" cellspacing="0" cellpadding="5" align="center">
+<tr>
+<td align="left"><font size="+1"><strong>Filename</strong></font></td>
+<td align="center"><font size="+1"><strong>Size</strong></font></td>
+<td align="right"><font size="+1"><strong>Last Modified</strong></font></td>
+</tr><tr>
+<td align="left">&nbsp;&nbsp;
+<a href="/files/backup/"><tt>backup/</tt></a></td>
+<td align="right"><tt>&nbsp;</tt></td>
+<td align="right"><tt>Thu, 01 Oct 2026 09:15:00 GMT</tt></td>
+</tr>
+<tr bgcolor="#eeeeee">
+<td align="left">&nbsp;&nbsp;
+<a href="/files/site.tar.gz"><tt>site.tar.gz</tt></a></td>
+<td align="right"><tt>4300.8 KiB</tt></td>
+<td align="right"><tt>Sat, 17 Oct 2026 22:03:41 GMT</tt></td>
+</tr>
+</table>
+<hr class="line"><h3>Apache Tomcat/10.1.31</h3></body>
+</html>"##;
+
+    const PYTHON: &str = r#"<!DOCTYPE HTML>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>Directory listing for /files/</title>
+</head>
+<body>
+<h1>Directory listing for /files/</h1>
+<hr>
+<ul>
+<li><a href="backup/">backup/</a></li>
+<li><a href="site.tar.gz">site.tar.gz</a></li>
+</ul>
+<hr>
+</body>
+</html>"#;
+
+    // An ordinary page which mentions the phrases used to detect listings
+    const NOT_A_LISTING: &str = r#"<html><head><title>Moving house</title></head>
+<body><h1>Moving house</h1>
+<p>Remember to update the parent directory of the school, and that the
+directory listing for the street is up to date.</p>
+<ul><li><a href="/files/contact.html">Contact us</a></li></ul>
+</body></html>"#;
+
+    // The times of the backup directory and the archive in the samples,
+    // for listings which only show them to the minute
+    const MINUTES: [Option<&str>; 2] =
+        [Some("2026-10-01 09:15:00"), Some("2026-10-17 22:03:00")];
+    const SECONDS: [Option<&str>; 2] =
+        [Some("2026-10-01 09:15:00"), Some("2026-10-17 22:03:41")];
+
+    fn assert_listing(
+        content: &str,
+        style: &str,
+        sizes: [Option<u64>; 2],
+        modified: [Option<&str>; 2],
+    ) {
+        let dir_url = Url::parse("http://example.com/files/").unwrap();
+        let listing = parse_listing(content, &dir_url).unwrap();
+        assert_eq!(listing.style, style);
+        assert_eq!(
+            listing
+                .entries
+                .iter()
+                .map(|entry| (
+                    entry.url.as_str(),
+                    entry.size,
+                    entry.modified.as_deref()
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                ("http://example.com/files/backup/", sizes[0], modified[0]),
+                (
+                    "http://example.com/files/site.tar.gz",
+                    sizes[1],
+                    modified[1]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn apache() {
+        assert_listing(APACHE, "apache", [None, Some(4404019)], MINUTES);
+        assert_listing(APACHE_PRE, "apache", [None, Some(4404019)], MINUTES);
+    }
+
+    #[test]
+    fn nginx() {
+        assert_listing(NGINX, "nginx", [None, Some(4404019)], MINUTES);
+    }
+
+    #[test]
+    fn lighttpd() {
+        assert_listing(LIGHTTPD, "lighttpd", [None, Some(4404019)], SECONDS);
+    }
+
+    #[test]
+    fn iis() {
+        assert_listing(IIS, "iis", [None, Some(4404019)], MINUTES);
+    }
+
+    #[test]
+    fn caddy() {
+        assert_listing(CADDY, "caddy", [None, Some(4404019)], SECONDS);
+    }
+
+    #[test]
+    fn jetty() {
+        assert_listing(JETTY, "jetty", [Some(4096), Some(4404019)], SECONDS);
+    }
+
+    #[test]
+    fn tomcat() {
+        assert_listing(TOMCAT, "tomcat", [None, Some(4404019)], SECONDS);
+    }
+
+    #[test]
+    fn python() {
+        assert_listing(PYTHON, "python", [None, None], [None, None]);
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("4404019"), Some(4404019));
+        assert_eq!(parse_size("4,404,019 bytes"), Some(4404019));
+        assert_eq!(parse_size("1.5K"), Some(1536));
+        assert_eq!(parse_size("2 MiB"), Some(2 << 20));
+        assert_eq!(parse_size("-"), None);
+        assert_eq!(parse_size("<dir>"), None);
+    }
+
+    #[test]
+    fn not_a_listing() {
+        let dir_url = Url::parse("http://example.com/files/").unwrap();
+        assert_eq!(parse_listing(NOT_A_LISTING, &dir_url), None);
+    }
+}
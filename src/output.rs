@@ -20,6 +20,8 @@ use crate::{
     output_format,
     request::RequestResponse,
 };
+use serde::Serialize;
+use simple_xml_serialize::XMLElement;
 use std::{
     fs::File,
     io::{LineWriter, Write},
@@ -35,6 +37,52 @@ pub struct FileHandles {
     pub xml_file: Option<LineWriter<File>>,
 }
 
+// What nonexistent paths looked like for a scanned directory, and
+// whether it was skipped because of it. These are included in the
+// reports so that it's clear why a directory had no results.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DirectoryBaseline {
+    pub url: String,
+    pub code: Option<u32>,
+    pub size: Option<i32>,
+    pub size_range: Option<(i32, i32)>,
+    pub diff_size: Option<i32>,
+    pub redirect_url: Option<String>,
+    pub similarity: Option<u8>,
+    pub alert: Option<&'static str>,
+    pub skipped: bool,
+}
+
+impl From<&DirectoryBaseline> for XMLElement {
+    fn from(baseline: &DirectoryBaseline) -> XMLElement {
+        let mut element =
+            XMLElement::new("directory").attr("url", &baseline.url);
+        if let Some(code) = baseline.code {
+            element.add_attr("code", code);
+        }
+        if let Some(size) = baseline.size {
+            element.add_attr("size", size);
+        }
+        if let Some((min, max)) = baseline.size_range {
+            element.add_attr("size_min", min);
+            element.add_attr("size_max", max);
+        }
+        if let Some(diff_size) = baseline.diff_size {
+            element.add_attr("diff_size", diff_size);
+        }
+        if let Some(redirect_url) = &baseline.redirect_url {
+            element.add_attr("redirect_url", redirect_url);
+        }
+        if let Some(similarity) = baseline.similarity {
+            element.add_attr("similarity", similarity);
+        }
+        if let Some(alert) = baseline.alert {
+            element.add_attr("alert", alert);
+        }
+        element.attr("skipped", baseline.skipped)
+    }
+}
+
 pub fn print_response(
     response: &RequestResponse,
     global_opts: Arc<GlobalOpts>,
@@ -69,6 +117,7 @@ pub fn print_response(
 // deals with saving to files too
 pub fn print_report(
     mut responses: Vec<Vec<RequestResponse>>,
+    mut baselines: Vec<DirectoryBaseline>,
     global_opts: Arc<GlobalOpts>,
     file_handles: FileHandles,
 ) {
@@ -76,6 +125,7 @@ pub fn print_report(
         //*response_list =
         sort_responses(response_list);
     }
+    baselines.sort_by(|a, b| a.url.cmp(&b.url));
 
     // If stdout is a terminal then write a report to it
     if global_opts.is_terminal {
@@ -122,31 +172,29 @@ pub fn print_report(
         }
     }
 
-    if !responses.is_empty() {
-        if let Some(mut handle) = file_handles.json_file {
-            write_file(&mut handle, String::from("["));
-            for response_list in &responses[0..responses.len() - 1] {
-                for response in response_list {
-                    let line =
-                        format!("{},\n", output_format::output_json(response));
-                    write_file(&mut handle, line);
-                }
-            }
-            let final_response_list = &responses[responses.len() - 1];
-            for response in
-                &final_response_list[0..final_response_list.len() - 1]
-            {
-                let line =
-                    format!("{},\n", output_format::output_json(response));
-                write_file(&mut handle, line);
-            }
-            let final_line = format!(
-                "{}]",
-                output_format::output_json(
-                    &final_response_list[final_response_list.len() - 1]
-                )
+    if let Some(mut handle) = file_handles.json_file {
+        let paths = responses
+            .iter()
+            .flatten()
+            .map(output_format::output_json)
+            .collect::<Vec<_>>();
+        // The report is only an object if the baselines are included, so
+        // that it stays a list of paths otherwise
+        if global_opts.report_baselines {
+            let directories = baselines
+                .iter()
+                .map(output_format::output_baseline_json)
+                .collect::<Vec<_>>();
+            write_file(
+                &mut handle,
+                format!(
+                    "{{\"paths\":[{}],\n\"directories\":[{}]}}",
+                    paths.join(",\n"),
+                    directories.join(",\n")
+                ),
             );
-            write_file(&mut handle, final_line);
+        } else {
+            write_file(&mut handle, format!("[{}]", paths.join(",\n")));
         }
     }
 
@@ -161,6 +209,14 @@ pub fn print_report(
                 write_file(&mut handle, output_format::output_xml(response));
             }
         }
+        if global_opts.report_baselines {
+            for baseline in &baselines {
+                write_file(
+                    &mut handle,
+                    output_format::output_baseline_xml(baseline),
+                );
+            }
+        }
         write_file(&mut handle, String::from("</dirble_scan>"));
     }
 }
@@ -314,15 +370,11 @@ mod test {
         // need to be tested - the rest is covered by the testing in
         // tests/output_format.rs.
         let rr = RequestResponse {
-            url: Url::parse("http://example.com/.htaccess").unwrap(),
             code: 403,
             content_len: 234,
-            is_directory: false,
-            is_listable: false,
-            redirect_url: Default::default(),
-            found_from_listable: false,
-            parent_index: 0,
-            parent_depth: 0,
+            ..RequestResponse::new(
+                Url::parse("http://example.com/.htaccess").unwrap(),
+            )
         };
 
         // Verify that htaccess files are hidden when the option is set in
@@ -362,15 +414,9 @@ mod test {
     #[test]
     fn test_sort_responses() {
         let mut rr = RequestResponse {
-            url: Url::parse("http://example.com/").unwrap(),
             code: 200,
             content_len: 200,
-            is_directory: false,
-            is_listable: false,
-            redirect_url: "".into(),
-            found_from_listable: false,
-            parent_index: 0,
-            parent_depth: 0,
+            ..RequestResponse::new(Url::parse("http://example.com/").unwrap())
         };
 
         // Generate a Vec of RequestResponses to sort
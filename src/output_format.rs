@@ -15,7 +15,7 @@
 // You should have received a copy of the GNU General Public License
 // along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::request::RequestResponse;
+use crate::{output::DirectoryBaseline, request::RequestResponse};
 use colored::*;
 use simple_xml_serialize::XMLElement;
 
@@ -50,7 +50,11 @@ pub fn output_indentation(
 
 #[inline]
 pub fn output_letter(response: &RequestResponse) -> String {
-    if response.is_directory && response.is_listable {
+    if response.baseline_drift {
+        "! ".bold().to_string()
+    } else if response.wildcard_directory {
+        "W ".bold().to_string()
+    } else if response.is_directory && response.is_listable {
         "L ".bold().to_string()
     } else if response.is_directory {
         String::from("D ")
@@ -69,7 +73,22 @@ pub fn output_url(response: &RequestResponse) -> String {
 #[inline]
 pub fn output_suffix(response: &RequestResponse, color: bool) -> String {
     if response.found_from_listable {
-        return String::from("(SCRAPED)");
+        let mut output = String::from("(SCRAPED");
+        if let Some(size) = response.listed_size {
+            output += &format!("|SIZE:{}", size);
+        }
+        if let Some(modified) = &response.listed_modified {
+            output += &format!("|MODIFIED:{}", modified);
+        }
+        return output + ")";
+    }
+
+    if response.baseline_drift {
+        return format!("(BASELINE CHANGED:{})", response.source);
+    }
+
+    if response.wildcard_directory {
+        return format!("(WILDCARD DIRECTORY:{})", response.source);
     }
 
     let mut code_string: String = format!("{}", response.code);
@@ -83,18 +102,37 @@ pub fn output_suffix(response: &RequestResponse, color: bool) -> String {
         }
     }
 
-    match response.code {
-        301 | 302 => format!(
-            "(CODE:{}|SIZE:{:#?}|DEST:{})",
-            code_string, response.content_len, response.redirect_url,
-        ),
-        _ => format!("(CODE:{}|SIZE:{:#?})", code_string, response.content_len),
+    let mut output =
+        format!("(CODE:{}|SIZE:{:#?}", code_string, response.content_len);
+
+    if let 301 | 302 = response.code {
+        output += &format!("|DEST:{}", response.redirect_url);
+    }
+
+    if !response.source.is_empty() {
+        output += &format!("|SOURCE:{}", response.source);
+    }
+
+    if !response.source_url.is_empty() {
+        output += &format!("|FROM:{}", response.source_url);
+    }
+
+    if let Some(target) = &response.requested_target {
+        output += &format!("|TARGET:{}", target);
     }
+
+    output + ")"
 }
 
 #[inline]
 pub fn output_xml(response: &RequestResponse) -> String {
-    format!("{}\n", XMLElement::from(response))
+    let mut element = XMLElement::from(response);
+    for header in &response.extra_headers {
+        let mut header_element = XMLElement::new("header");
+        header_element.set_text(header);
+        element.add_element(header_element);
+    }
+    format!("{}\n", element)
 }
 
 #[inline]
@@ -102,6 +140,16 @@ pub fn output_json(response: &RequestResponse) -> String {
     serde_json::to_string(response).unwrap()
 }
 
+#[inline]
+pub fn output_baseline_xml(baseline: &DirectoryBaseline) -> String {
+    format!("{}\n", XMLElement::from(baseline))
+}
+
+#[inline]
+pub fn output_baseline_json(baseline: &DirectoryBaseline) -> String {
+    serde_json::to_string(baseline).unwrap()
+}
+
 #[cfg(test)]
 mod test {
     use url::Url;
@@ -221,6 +269,21 @@ mod test {
             "+ ",
             "Regular file prefix incorrect"
         );
+
+        req_response.baseline_drift = true;
+        assert_eq!(
+            crate::output_format::output_letter(&req_response),
+            "\u{1b}[1m! \u{1b}[0m",
+            "Baseline drift prefix incorrect"
+        );
+
+        req_response.baseline_drift = false;
+        req_response.wildcard_directory = true;
+        assert_eq!(
+            crate::output_format::output_letter(&req_response),
+            "\u{1b}[1mW \u{1b}[0m",
+            "Wildcard directory prefix incorrect"
+        );
     }
 
     #[test]
@@ -284,21 +347,84 @@ mod test {
             "(CODE:503|SIZE:456)",
             "Disabling colours hasn't worked properly"
         );
+
+        // Test that the source is included
+        req_response.code = 200;
+        req_response.source = "403 bypass (trailing ;/)".into();
+        req_response.source_url = "http://example.com/admin;/".into();
+        assert_eq!(
+            crate::output_format::output_suffix(&req_response, false),
+            "(CODE:200|SIZE:456|SOURCE:403 bypass (trailing ;/)\
+             |FROM:http://example.com/admin;/)",
+            "Output suffix with a source invalid"
+        );
+
+        // Test that the target sent is included
+        req_response.source.clear();
+        req_response.source_url.clear();
+        req_response.requested_target = Some("/pages/../x.php".into());
+        assert_eq!(
+            crate::output_format::output_suffix(&req_response, false),
+            "(CODE:200|SIZE:456|TARGET:/pages/../x.php)",
+            "Output suffix with a requested target invalid"
+        );
+
+        // Baseline drift entries describe the change instead
+        req_response.baseline_drift = true;
+        req_response.source = "(CODE:404) to (CODE:403) between 10:00:00 \
+                               and 10:05:00"
+            .into();
+        assert_eq!(
+            crate::output_format::output_suffix(&req_response, false),
+            "(BASELINE CHANGED:(CODE:404) to (CODE:403) between 10:00:00 \
+             and 10:05:00)",
+            "Output suffix for baseline drift invalid"
+        );
+
+        // As do wildcard directories
+        req_response.baseline_drift = false;
+        req_response.wildcard_directory = true;
+        req_response.source = "97 of 100 paths found".into();
+        assert_eq!(
+            crate::output_format::output_suffix(&req_response, false),
+            "(WILDCARD DIRECTORY:97 of 100 paths found)",
+            "Output suffix for wildcard directory invalid"
+        );
+
+        // Scraped paths include what the listing showed for them
+        req_response.wildcard_directory = false;
+        req_response.found_from_listable = true;
+        assert_eq!(
+            crate::output_format::output_suffix(&req_response, false),
+            "(SCRAPED)",
+            "Output suffix for scraped path invalid"
+        );
+        req_response.listed_size = Some(4404019);
+        req_response.listed_modified = Some("2026-10-17 22:03:41".into());
+        assert_eq!(
+            crate::output_format::output_suffix(&req_response, false),
+            "(SCRAPED|SIZE:4404019|MODIFIED:2026-10-17 22:03:41)",
+            "Output suffix for scraped path with metadata invalid"
+        );
     }
 
     #[test]
     fn check_output_xml() {
         // Same as check_output_json below, but with hardcoded XML output.
         let req_response = crate::request::RequestResponse {
-            url: Url::parse("http://example.com").unwrap(),
             code: 204,
             content_len: 345,
-            is_directory: false,
-            is_listable: false,
             found_from_listable: true,
             redirect_url: "https://example.org".into(),
-            parent_index: 0,
             parent_depth: 2,
+            total_time: 120,
+            time_to_first_byte: 80,
+            connect_time: 10,
+            listed_size: Some(4404019),
+            listed_modified: Some("2026-10-17 22:03:41".into()),
+            ..crate::request::RequestResponse::new(
+                Url::parse("http://example.com").unwrap(),
+            )
         };
         // DO NOT change the indentation here, it matches the indentation
         // produced by the XML formatter.
@@ -312,7 +438,16 @@ mod test {
             is_directory=\"false\" \
             is_listable=\"false\" \
             redirect_url=\"https://example.org\" \
-            found_from_listable=\"true\"\
+            found_from_listable=\"true\" \
+            total_time=\"120\" \
+            time_to_first_byte=\"80\" \
+            connect_time=\"10\" \
+            source=\"\" \
+            source_url=\"\" \
+            baseline_drift=\"false\" \
+            wildcard_directory=\"false\" \
+            listed_size=\"4404019\" \
+            listed_modified=\"2026-10-17 22:03:41\"\
         />\n",
         "XML format invalid");
     }
@@ -323,15 +458,13 @@ mod test {
         // the defaults may change but the expected JSON output is
         // hardcoded.
         let req_response = crate::request::RequestResponse {
-            url: Url::parse("http://example.com").unwrap(),
             code: 200,
             content_len: 350,
-            is_directory: false,
             is_listable: true,
             redirect_url: "https://example.org".into(),
-            found_from_listable: false,
-            parent_index: 0,
-            parent_depth: 0,
+            ..crate::request::RequestResponse::new(
+                Url::parse("http://example.com").unwrap(),
+            )
         };
 
         /*assert_tokens(
@@ -360,7 +493,33 @@ mod test {
         );*/
         assert_eq!(
             serde_json::to_string(&req_response).unwrap(),
-            "{\"url\":\"http://example.com/\",\"code\":200,\"size\":350,\"is_directory\":false,\"is_listable\":true,\"redirect_url\":\"https://example.org\",\"found_from_listable\":false}"
+            "{\"url\":\"http://example.com/\",\"code\":200,\"size\":350,\"is_directory\":false,\"is_listable\":true,\"redirect_url\":\"https://example.org\",\"found_from_listable\":false,\"total_time\":0,\"time_to_first_byte\":0,\"connect_time\":0,\"source\":\"\",\"source_url\":\"\",\"requested_target\":null,\"bypass_technique\":null,\"extra_headers\":[],\"baseline_drift\":false,\"wildcard_directory\":false,\"listed_size\":null,\"listed_modified\":null}"
+        );
+    }
+
+    #[test]
+    fn check_output_baseline() {
+        let baseline = crate::output::DirectoryBaseline {
+            url: "http://example.com/admin/".into(),
+            code: Some(403),
+            size: None,
+            size_range: Some((100, 140)),
+            diff_size: None,
+            redirect_url: None,
+            similarity: None,
+            alert: Some("CODE_403"),
+            skipped: true,
+        };
+
+        assert_eq!(
+            crate::output_format::output_baseline_xml(&baseline),
+            "<directory url=\"http://example.com/admin/\" code=\"403\" \
+             size_min=\"100\" size_max=\"140\" alert=\"CODE_403\" \
+             skipped=\"true\"/>\n"
+        );
+        assert_eq!(
+            crate::output_format::output_baseline_json(&baseline),
+            "{\"url\":\"http://example.com/admin/\",\"code\":403,\"size\":null,\"size_range\":[100,140],\"diff_size\":null,\"redirect_url\":null,\"similarity\":null,\"alert\":\"CODE_403\",\"skipped\":true}"
         );
     }
 
@@ -369,15 +528,12 @@ mod test {
         // Generate a RequestResponse object with sane default settings to
         // simplify the testing routines.
         crate::request::RequestResponse {
-            url: Url::parse("http://example.com").unwrap(),
             code: 200,
             content_len: 350,
-            is_directory: false,
-            is_listable: false,
-            found_from_listable: false,
             redirect_url: "https://example.org".into(),
-            parent_index: 0,
-            parent_depth: 0,
+            ..crate::request::RequestResponse::new(
+                Url::parse("http://example.com").unwrap(),
+            )
         }
     }
 }
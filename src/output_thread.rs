@@ -15,7 +15,7 @@
 // You should have received a copy of the GNU General Public License
 // along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::{arg_parse, output, request};
+use crate::{arg_parse, output, output::DirectoryBaseline, request};
 use log::debug;
 use simplelog::LevelFilter;
 use std::sync::{Arc, mpsc};
@@ -23,6 +23,7 @@ use url::Url;
 
 pub fn output_thread(
     rx: mpsc::Receiver<request::RequestResponse>,
+    baseline_rx: mpsc::Receiver<DirectoryBaseline>,
     global_opts: Arc<arg_parse::GlobalOpts>,
     file_handles: output::FileHandles,
 ) {
@@ -35,20 +36,24 @@ pub fn output_thread(
                 debug!("Received signal to end, generating the report");
                 break;
             }
-            if global_opts.log_level >= LevelFilter::Info {
-                if let Some(output) = output::print_response(
+            if global_opts.log_level >= LevelFilter::Info
+                && let Some(output) = output::print_response(
                     &response,
                     global_opts.clone(),
                     false,
                     false,
                     global_opts.is_terminal && !global_opts.no_color,
-                ) {
-                    println!("{}", output);
-                }
+                )
+            {
+                println!("{}", output);
             }
             response_list[response.parent_index].push(response);
         }
     }
 
-    output::print_report(response_list, global_opts, file_handles);
+    // Main closes the baseline channel before signalling the end, so
+    // this gets all of them
+    let baselines = baseline_rx.iter().collect();
+
+    output::print_report(response_list, baselines, global_opts, file_handles);
 }
@@ -16,40 +16,106 @@
 // along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::{
-    arg_parse::{GlobalOpts, HttpVerb},
-    content_parse,
+    arg_parse::{GlobalOpts, HttpVerb, PathMode},
+    content_parse, har, listing, validator_thread,
 };
 use curl::{
     Error,
-    easy::{Easy2, Handler, WriteError},
+    easy::{Easy2, Handler, InfoType, WriteError},
 };
 use log::trace;
 use percent_encoding::percent_decode;
+use rand::seq::IndexedRandom;
 use serde::{Serialize, Serializer, ser::SerializeStruct};
 use simple_xml_serialize::XMLElement;
 use simple_xml_serialize_macro::xml_element;
-use std::{sync::Arc, time::Duration};
+use std::{sync::Arc, thread, time::Duration};
 use url::Url;
 
 pub struct Collector {
     contents: Vec<u8>,
+    max_body_size: Option<usize>,
+    truncated: bool,
+    global_opts: Arc<GlobalOpts>,
+    // Set when the headers of the last request differ from the defaults
+    headers_modified: bool,
+    // The headers sent and received are only kept if they're going to be
+    // recorded
+    capture_headers: bool,
+    request_headers: Vec<u8>,
+    response_headers: Vec<String>,
+    har_tx: har::HarSender,
 }
 
 impl Collector {
     fn clear_buffer(&mut self) {
         self.contents = Vec::new();
+        self.truncated = false;
+        self.request_headers = Vec::new();
+        self.response_headers = Vec::new();
     }
 
     fn len(&self) -> usize {
         self.contents.len()
     }
+
+    pub fn contents(&self) -> &[u8] {
+        &self.contents
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    pub fn request_headers(&self) -> &[u8] {
+        &self.request_headers
+    }
+
+    pub fn response_headers(&self) -> &[String] {
+        &self.response_headers
+    }
+
+    pub fn http_verb(&self) -> HttpVerb {
+        self.global_opts.http_verb
+    }
 }
 
 impl Handler for Collector {
     fn write(&mut self, data: &[u8]) -> Result<usize, WriteError> {
+        // If the body would exceed the maximum size then keep what fits
+        // and report a short write, which makes curl abort the transfer
+        if let Some(max_body_size) = self.max_body_size {
+            let remaining = max_body_size.saturating_sub(self.contents.len());
+            if data.len() > remaining {
+                self.contents.extend_from_slice(&data[..remaining]);
+                self.truncated = true;
+                return Ok(remaining);
+            }
+        }
         self.contents.extend_from_slice(data);
         Ok(data.len())
     }
+
+    fn header(&mut self, data: &[u8]) -> bool {
+        if self.capture_headers {
+            let line = String::from_utf8_lossy(data).to_string();
+            // Only keep the headers of the final response, e.g. not those
+            // of a "100 Continue"
+            if line.starts_with("HTTP/") {
+                self.response_headers.clear();
+            }
+            self.response_headers.push(line);
+        }
+        true
+    }
+
+    // This is only called when verbose output is enabled, which is when
+    // the headers are being captured
+    fn debug(&mut self, kind: InfoType, data: &[u8]) {
+        if self.capture_headers && matches!(kind, InfoType::HeaderOut) {
+            self.request_headers = data.to_vec();
+        }
+    }
 }
 
 // Struct which contains information about a response
@@ -71,6 +137,46 @@ pub struct RequestResponse {
     pub redirect_url: String,
     #[sxs_type_attr]
     pub found_from_listable: bool,
+    // Timings of the request, given in milliseconds
+    #[sxs_type_attr]
+    pub total_time: u64,
+    #[sxs_type_attr]
+    pub time_to_first_byte: u64,
+    #[sxs_type_attr]
+    pub connect_time: u64,
+    // How the path was found if not from the wordlist, and the url it
+    // was found from
+    #[sxs_type_attr]
+    pub source: String,
+    #[sxs_type_attr]
+    pub source_url: String,
+    // The target that was sent in the request line, if it isn't the url,
+    // such as a path sent in raw mode
+    #[sxs_type_attr]
+    pub requested_target: Option<String>,
+    // The technique which got around a 403, and the headers it added to
+    // the request. The headers are written as elements in XML reports.
+    #[sxs_type_attr]
+    pub bypass_technique: Option<String>,
+    pub extra_headers: Vec<String>,
+    // Set for entries marking a change in the not found responses of a
+    // directory during the scan, rather than a path
+    #[sxs_type_attr]
+    pub baseline_drift: bool,
+    // Set for entries marking a directory which was stopped being scanned
+    // because almost every path in it was found
+    #[sxs_type_attr]
+    pub wildcard_directory: bool,
+    // The size and modification time shown for the path in the directory
+    // listing it was scraped from, if the listing has them
+    #[sxs_type_attr]
+    pub listed_size: Option<u64>,
+    #[sxs_type_attr]
+    pub listed_modified: Option<String>,
+    // Fuzzy hash of the body, only set if similarity detection is enabled
+    pub content_hash: Option<u64>,
+    // Set if the response matched one of the user's not found rules
+    pub not_found_rule: bool,
     pub parent_index: usize,
     pub parent_depth: u32,
 }
@@ -80,7 +186,7 @@ impl Serialize for RequestResponse {
     where
         S: Serializer,
     {
-        let mut s = serializer.serialize_struct("RequestResponse", 8)?;
+        let mut s = serializer.serialize_struct("RequestResponse", 19)?;
         s.serialize_field("url", &self.url.as_str())?;
         s.serialize_field("code", &self.code)?;
         s.serialize_field("size", &self.content_len)?;
@@ -88,11 +194,53 @@ impl Serialize for RequestResponse {
         s.serialize_field("is_listable", &self.is_listable)?;
         s.serialize_field("redirect_url", &self.redirect_url)?;
         s.serialize_field("found_from_listable", &self.found_from_listable)?;
+        s.serialize_field("total_time", &self.total_time)?;
+        s.serialize_field("time_to_first_byte", &self.time_to_first_byte)?;
+        s.serialize_field("connect_time", &self.connect_time)?;
+        s.serialize_field("source", &self.source)?;
+        s.serialize_field("source_url", &self.source_url)?;
+        s.serialize_field("requested_target", &self.requested_target)?;
+        s.serialize_field("bypass_technique", &self.bypass_technique)?;
+        s.serialize_field("extra_headers", &self.extra_headers)?;
+        s.serialize_field("baseline_drift", &self.baseline_drift)?;
+        s.serialize_field("wildcard_directory", &self.wildcard_directory)?;
+        s.serialize_field("listed_size", &self.listed_size)?;
+        s.serialize_field("listed_modified", &self.listed_modified)?;
         s.end()
     }
 }
 
 impl RequestResponse {
+    // Creates a response for the url with everything else empty, which
+    // the fields that are known can be set on
+    pub fn new(url: Url) -> RequestResponse {
+        RequestResponse {
+            url,
+            code: 0,
+            content_len: 0,
+            is_directory: false,
+            is_listable: false,
+            redirect_url: String::new(),
+            found_from_listable: false,
+            total_time: 0,
+            time_to_first_byte: 0,
+            connect_time: 0,
+            source: String::new(),
+            source_url: String::new(),
+            requested_target: None,
+            bypass_technique: None,
+            extra_headers: Vec::new(),
+            baseline_drift: false,
+            wildcard_directory: false,
+            listed_size: None,
+            listed_modified: None,
+            content_hash: None,
+            not_found_rule: false,
+            parent_index: 0,
+            parent_depth: 0,
+        }
+    }
+
     pub fn get_depth(&self) -> i32 {
         let mut depth = self.url.as_str().matches('/').count() as i32;
 
@@ -111,45 +259,53 @@ impl RequestResponse {
 // It then makes the request, if the response was not a 404
 // then it will return a RequestResponse struct
 pub fn make_request(easy: &mut Easy2<Collector>, url: Url) -> RequestResponse {
-    trace!("Requesting {}", url);
+    let target = url.to_string();
+    make_custom_request(easy, &target, &[], url)
+}
+
+// Makes a request to the target string exactly as given, with any extra
+// headers added, and records the response against the given url. This is
+// used when the url that is requested can't be represented as a Url
+// without being normalised.
+pub fn make_custom_request(
+    easy: &mut Easy2<Collector>,
+    target: &str,
+    extra_headers: &[String],
+    url: Url,
+) -> RequestResponse {
+    trace!("Requesting {}", target);
     // Set the url in the Easy2 instance
-    easy.url(url.as_str()).unwrap();
+    easy.url(target).unwrap();
+
+    // Pick new values for any rotated user agent or headers
+    set_request_options(easy, extra_headers);
 
     // Perform the request and check if it's empty
     // If it's empty then return a RequestResponse struct
     match perform(easy) {
         Ok(_v) => {}
+        // Transfers aborted due to the maximum body size being reached
+        // still have a valid response
+        Err(e) if e.is_write_error() && easy.get_ref().truncated => {}
         Err(e) => {
-            println!("Curl error after requesting {} : {}", url, e);
-            let req_response = RequestResponse {
-                url,
-                code: 0,
-                content_len: 0,
-                is_directory: false,
-                is_listable: false,
-                redirect_url: String::from(""),
-                found_from_listable: false,
-                parent_index: 0,
-                parent_depth: 0,
-            };
-            return req_response;
+            println!("Curl error after requesting {} : {}", target, e);
+            record_har(easy, target, Some(&e));
+            return RequestResponse::new(url);
         }
     }
 
+    record_har(easy, target, None);
+
     // Get the response code
     let code = easy.response_code().unwrap();
 
     // Declare the RequestResponse for the current request
     let mut req_response = RequestResponse {
-        url: url.clone(),
         code,
-        content_len: 0,
-        is_directory: false,
-        is_listable: false,
-        redirect_url: String::from(""),
-        found_from_listable: false,
-        parent_index: 0,
-        parent_depth: 0,
+        total_time: duration_millis(easy.total_time()),
+        time_to_first_byte: duration_millis(easy.starttransfer_time()),
+        connect_time: duration_millis(easy.connect_time()),
+        ..RequestResponse::new(url.clone())
     };
 
     // If the response was a redirect, check if it's a directory
@@ -173,9 +329,37 @@ pub fn make_request(easy: &mut Easy2<Collector>, url: Url) -> RequestResponse {
         req_response.redirect_url = redir_dest.to_string();
     }
 
-    // Get the contents of the response and set the length in the struct
+    // Hash the body so that it can be compared to not found responses.
+    // Empty bodies, such as for HEAD requests, would all be identical so
+    // aren't compared.
+    if easy.get_ref().global_opts.similarity.is_some() {
+        let content = get_content(easy);
+        if !content.is_empty() {
+            req_response.content_hash = Some(content_parse::simhash(&content));
+        }
+    }
+
+    // Get the contents of the response and set the length in the struct.
+    // If the body was cut short or there wasn't one (e.g. for HEAD
+    // requests) then use the declared Content-Length where possible.
+    let declared_len = easy.content_length_download().unwrap_or(-1.0);
     let contents = easy.get_ref();
-    req_response.content_len = contents.len();
+    req_response.content_len =
+        if (contents.truncated || contents.len() == 0) && declared_len >= 0.0 {
+            declared_len as usize
+        } else {
+            contents.len()
+        };
+
+    // Check the user's not found rules while the body is available
+    let global_opts = easy.get_ref().global_opts.clone();
+    if !global_opts.not_found_rules.is_empty() {
+        req_response.not_found_rule = validator_thread::matches_not_found_rule(
+            &global_opts.not_found_rules,
+            &req_response,
+            &get_content(easy),
+        );
+    }
 
     req_response
 }
@@ -193,118 +377,138 @@ pub fn listable_check(
     if !dir_url.ends_with('/') {
         dir_url += "/";
     }
-    let mut response =
-        make_request(easy, Url::parse(dir_url.as_str()).unwrap());
-    let content = get_content(easy).to_lowercase();
+    let dir_url = Url::parse(dir_url.as_str()).unwrap();
+    let mut response = make_request(easy, dir_url.clone());
     let mut output_list: Vec<RequestResponse> = Vec::new();
 
-    match response.code {
-        // If a found response was returned then check if the directory
-        // is listable or not
-        200 => {
-            let listable = content.contains("parent directory")
-                || content.contains("up to ")
-                || content.contains("directory listing for");
-
-            if listable {
-                response.is_listable = true;
-                response.is_directory = true;
-                output_list.push(response);
-            } else {
-                response.is_listable = false;
-                response.is_directory = true;
-
-                output_list.push(response);
-                return output_list;
-            }
-        }
-        // If the code returned was not a 200 then create a struct
-        // indicating that this is a folder, then return it
-        _ => {
-            response.is_directory = true;
-            response.is_listable = false;
-            output_list.push(response);
-            return output_list;
-        }
-    }
+    // Only a found response can be a listing
+    let listing = match response.code {
+        200 => listing::parse_listing(&get_content(easy), &dir_url),
+        _ => None,
+    };
+    response.is_directory = true;
+    response.is_listable = listing.is_some();
+    output_list.push(response);
 
-    // If scraping of listables is disabled then just return from the
-    // function
+    // If the directory isn't listable, or scraping of listables is
+    // disabled then just return from the function
+    let Some(listing) = listing else {
+        return output_list;
+    };
+    trace!("{} is a {} directory listing", dir_url, listing.style);
     if !scrape_listable {
         return output_list;
     }
 
-    // Get urls scraped from the response
-    let scraped_urls: Vec<String> =
-        content_parse::scrape_urls(content, dir_url);
-
-    for scraped_url in scraped_urls {
+    for entry in listing.entries {
+        let scraped_url = entry.url.as_str();
         // If the scraped url doesn't end in a /, it's unlikely to be a
         // folder
         // Add it to the list of found URLs to be returned
         if !scraped_url.ends_with('/') {
-            output_list.push(fabricate_request_response(
-                Url::parse(scraped_url.as_str()).unwrap(),
-                false,
-                false,
-            ));
+            let mut scraped =
+                fabricate_request_response(entry.url.clone(), false, false);
+            scraped.listed_size = entry.size;
+            scraped.listed_modified = entry.modified;
+            output_list.push(scraped);
         }
         // If the url ends in a /, it is likely to be a folder
         else {
             // If the max recursion depth is set, calculate the URL's depth
             // If the max depth is exceeded then just add the URL to the list
             // Otherwise call this function on the scraped URL
-            if let Some(max_depth) = max_recursion_depth {
-                let mut depth = scraped_url.matches('/').count() as i32;
-
-                if scraped_url.ends_with('/') {
-                    depth -= 1;
-                }
-
-                depth -= parent_depth;
-
-                // If we've exceeded the max depth, add the url to the
-                // values to be returned
-                if depth > max_depth {
-                    output_list.push(fabricate_request_response(
-                        Url::parse(scraped_url.as_str()).unwrap(),
-                        true,
-                        false,
-                    ));
-                } else {
-                    output_list.append(&mut listable_check(
-                        easy,
-                        Url::parse(scraped_url.as_str()).unwrap(),
-                        max_recursion_depth,
-                        parent_depth,
-                        scrape_listable,
-                    ));
-                }
-            }
-            // If there is no limit to recursion depth
-            // then call this function on the discovered folder
-            else {
-                output_list.append(&mut listable_check(
+            let depth_exceeded = max_recursion_depth.is_some_and(|max_depth| {
+                let depth = scraped_url.matches('/').count() as i32 - 1;
+                depth - parent_depth > max_depth
+            });
+
+            let mut scraped = if depth_exceeded {
+                vec![fabricate_request_response(entry.url.clone(), true, false)]
+            } else {
+                listable_check(
                     easy,
-                    Url::parse(scraped_url.as_str()).unwrap(),
+                    entry.url.clone(),
                     max_recursion_depth,
                     parent_depth,
                     scrape_listable,
-                ));
-            }
+                )
+            };
+            // The first response is for the directory itself
+            scraped[0].listed_size = entry.size;
+            scraped[0].listed_modified = entry.modified;
+            output_list.append(&mut scraped);
         }
     }
 
     output_list
 }
 
+// Sends the request that was last made to be written to the HAR file, if
+// there is one
+fn record_har(
+    easy: &mut Easy2<Collector>,
+    target: &str,
+    error: Option<&Error>,
+) {
+    let Some(har_tx) = easy.get_ref().har_tx.clone() else {
+        return;
+    };
+    let max_body_size = easy.get_ref().global_opts.har_body_size;
+    let entry = har::Entry::from_easy(easy, target, error, max_body_size);
+    // The HAR thread stops early if the scan is interrupted
+    let _ = har_tx.send(Some(entry));
+}
+
+// Sleeps between requests if a throttle has been set
+pub fn throttle(global_opts: &GlobalOpts) {
+    if global_opts.throttle != 0 {
+        thread::sleep(Duration::from_millis(u64::from(global_opts.throttle)));
+    }
+}
+
+// Paths ending in a slash are only directories if the path without the
+// slash redirects to them, the same as for paths found by the scan
+pub fn is_directory(
+    easy: &mut Easy2<Collector>,
+    url: &Url,
+    global_opts: &GlobalOpts,
+) -> bool {
+    let path = url.path();
+    if path == "/" || !path.ends_with('/') {
+        return false;
+    }
+
+    let mut file_url = url.clone();
+    file_url.set_path(&path[..path.len() - 1]);
+    let response = make_request(easy, file_url);
+    throttle(global_opts);
+    response.is_directory
+}
+
 // Creates an easy2 instance based on the parameters provided by the user
-pub fn generate_easy(global_opts: &Arc<GlobalOpts>) -> Easy2<Collector> {
+pub fn generate_easy(
+    global_opts: &Arc<GlobalOpts>,
+    har_tx: &har::HarSender,
+) -> Easy2<Collector> {
     // Create a new curl Easy2 instance and set it to use GET requests
     let mut easy = Easy2::new(Collector {
         contents: Vec::new(),
+        max_body_size: global_opts.max_body_size,
+        truncated: false,
+        global_opts: global_opts.clone(),
+        headers_modified: false,
+        capture_headers: har_tx.is_some(),
+        request_headers: Vec::new(),
+        response_headers: Vec::new(),
+        har_tx: har_tx.clone(),
     });
 
+    // The request headers are passed to the debug handler, which is only
+    // used in verbose mode
+    if har_tx.is_some() {
+        easy.verbose(true).unwrap();
+    }
+
     match &global_opts.http_verb {
         HttpVerb::Get => {
             easy.get(true).unwrap();
@@ -317,6 +521,11 @@ pub fn generate_easy(global_opts: &Arc<GlobalOpts>) -> Easy2<Collector> {
         }
     }
 
+    // Stop curl from normalising paths which are meant to be sent as is
+    if global_opts.path_mode != PathMode::Normal {
+        easy.path_as_is(true).unwrap();
+    }
+
     // Set the timeout of the easy
     easy.timeout(Duration::from_secs(u64::from(global_opts.timeout)))
         .unwrap();
@@ -358,9 +567,75 @@ pub fn generate_easy(global_opts: &Arc<GlobalOpts>) -> Easy2<Collector> {
         easy.http_headers(header_list).unwrap();
     }
 
+    // Connect over a Unix domain socket if one has been provided
+    if let Some(unix_socket) = &global_opts.unix_socket {
+        easy.unix_socket_path(Some(unix_socket)).unwrap();
+    }
+
+    // Override name resolution for the given hosts
+    if let Some(resolve) = &global_opts.resolve {
+        let mut resolve_list = curl::easy::List::new();
+        for entry in resolve {
+            resolve_list.append(entry).unwrap();
+        }
+        easy.resolve(resolve_list).unwrap();
+    }
+
+    // Redirect connections for the given hosts to different addresses
+    if let Some(connect_to) = &global_opts.connect_to {
+        let mut connect_to_list = curl::easy::List::new();
+        for entry in connect_to {
+            connect_to_list.append(entry).unwrap();
+        }
+        easy.connect_to(connect_to_list).unwrap();
+    }
+
     easy
 }
 
+// Sets a randomly chosen user agent and random header values on the easy
+// if rotation of them has been requested, along with any extra headers
+// for this request
+fn set_request_options(easy: &mut Easy2<Collector>, extra_headers: &[String]) {
+    let global_opts = easy.get_ref().global_opts.clone();
+    let mut rng = rand::rng();
+
+    if let Some(user_agent) = global_opts
+        .user_agents
+        .as_ref()
+        .and_then(|user_agents| user_agents.choose(&mut rng))
+    {
+        easy.useragent(user_agent).unwrap();
+    }
+
+    // Setting the headers replaces any set previously, so the fixed
+    // headers need to be included too. The headers also need resetting
+    // if the previous request had extra ones.
+    if global_opts.random_headers.is_none()
+        && extra_headers.is_empty()
+        && !easy.get_ref().headers_modified
+    {
+        return;
+    }
+
+    let mut header_list = curl::easy::List::new();
+    for header in global_opts.headers.iter().flatten() {
+        header_list.append(header).unwrap();
+    }
+    for random_header in global_opts.random_headers.iter().flatten() {
+        if let Some(value) = random_header.values.choose(&mut rng) {
+            header_list
+                .append(&format!("{}: {}", random_header.name, value))
+                .unwrap();
+        }
+    }
+    for header in extra_headers {
+        header_list.append(header).unwrap();
+    }
+    easy.http_headers(header_list).unwrap();
+    easy.get_mut().headers_modified = !extra_headers.is_empty();
+}
+
 // Before each request, the buffer should be cleared
 // This provides support for chunked http responses
 fn perform(easy: &mut Easy2<Collector>) -> Result<(), Error> {
@@ -368,6 +643,12 @@ fn perform(easy: &mut Easy2<Collector>) -> Result<(), Error> {
     easy.perform()
 }
 
+// Convert a timing from curl into milliseconds, treating errors as 0
+#[inline]
+fn duration_millis(duration: Result<Duration, Error>) -> u64 {
+    duration.map_or(0, |duration| duration.as_millis() as u64)
+}
+
 // Get the current content of the given easy and return it as a string
 fn get_content(easy: &mut Easy2<Collector>) -> String {
     let contents = easy.get_ref();
@@ -382,14 +663,225 @@ pub fn fabricate_request_response(
     is_listable: bool,
 ) -> RequestResponse {
     RequestResponse {
-        url,
-        code: 0,
-        content_len: 0,
         is_directory,
         is_listable,
-        redirect_url: String::from(""),
         found_from_listable: true,
-        parent_index: 0,
-        parent_depth: 0,
+        ..RequestResponse::new(url)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        arg_parse::{GlobalOpts, HttpVerb},
+        request::{
+            RequestResponse, generate_easy, listable_check, make_request,
+        },
+    };
+    use std::sync::Arc;
+    use url::Url;
+
+    #[test]
+    fn content_length() {
+        let port = crate::test_server::launch();
+        let url: Url = format!("http://localhost:{port}/ok").parse().unwrap();
+
+        // Without a limit the whole body is downloaded
+        let mut easy = generate_easy(&Arc::new(GlobalOpts::default()), &None);
+        let response = make_request(&mut easy, url.clone());
+        assert_eq!(response.code, 200);
+        assert_eq!(response.content_len, 10);
+        assert_eq!(easy.get_ref().len(), 10);
+
+        // With a limit the body is cut short, but the declared length
+        // is still reported
+        let mut easy = generate_easy(
+            &Arc::new(GlobalOpts {
+                max_body_size: Some(4),
+                ..Default::default()
+            }),
+            &None,
+        );
+        let response = make_request(&mut easy, url.clone());
+        assert_eq!(response.code, 200);
+        assert_eq!(response.content_len, 10);
+        assert_eq!(easy.get_ref().len(), 4);
+
+        // HEAD requests have no body, so the length comes from the
+        // Content-Length header
+        let mut easy = generate_easy(
+            &Arc::new(GlobalOpts {
+                http_verb: HttpVerb::Head,
+                ..Default::default()
+            }),
+            &None,
+        );
+        let response = make_request(&mut easy, url);
+        assert_eq!(response.code, 200);
+        assert_eq!(response.content_len, 10);
+    }
+
+    #[test]
+    fn content_hash() {
+        let port = crate::test_server::launch();
+        let url: Url = format!("http://localhost:{port}/ok").parse().unwrap();
+
+        let mut easy = generate_easy(
+            &Arc::new(GlobalOpts {
+                similarity: Some(90),
+                ..Default::default()
+            }),
+            &None,
+        );
+        assert!(make_request(&mut easy, url.clone()).content_hash.is_some());
+
+        // Without a body there's nothing to compare
+        let mut easy = generate_easy(
+            &Arc::new(GlobalOpts {
+                similarity: Some(90),
+                http_verb: HttpVerb::Head,
+                ..Default::default()
+            }),
+            &None,
+        );
+        assert!(make_request(&mut easy, url).content_hash.is_none());
+    }
+
+    #[test]
+    fn rotating_headers() {
+        use std::{collections::HashSet, io::Write};
+
+        let port = crate::test_server::launch();
+        let url: Url = format!("http://localhost:{port}/echo-headers")
+            .parse()
+            .unwrap();
+
+        let mut user_agent_file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(user_agent_file, "agent-one\nagent-two\nagent-three").unwrap();
+        let mut header_file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(header_file, "value-one\nvalue-two").unwrap();
+
+        let global_opts = crate::arg_parse::get_args([
+            "dirble".into(),
+            format!("http://localhost:{port}"),
+            "--user-agent-file".into(),
+            user_agent_file.path().to_str().unwrap().into(),
+            "--random-header".into(),
+            format!("X-Rotate:{}", header_file.path().to_str().unwrap()),
+        ]);
+        let mut easy = generate_easy(&Arc::new(global_opts), &None);
+
+        // Each request picks its values at random, so over enough requests
+        // every value in the files is sent
+        let mut user_agents = HashSet::new();
+        let mut header_values = HashSet::new();
+        for _ in 0..50 {
+            make_request(&mut easy, url.clone());
+            let body =
+                String::from_utf8_lossy(easy.get_ref().contents()).to_string();
+            let (user_agent, header_value) = body.split_once('|').unwrap();
+            user_agents.insert(user_agent.to_string());
+            header_values.insert(header_value.to_string());
+        }
+        assert_eq!(
+            user_agents,
+            HashSet::from(
+                ["agent-one", "agent-two", "agent-three"].map(String::from)
+            )
+        );
+        assert_eq!(
+            header_values,
+            HashSet::from(["value-one", "value-two"].map(String::from))
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unix_socket() {
+        let socket_dir = tempfile::tempdir().unwrap();
+        let socket_path = socket_dir.path().join("dirble.sock");
+        crate::test_server::launch_unix(&socket_path);
+
+        // The host in the URL is ignored, the request goes to the socket
+        let mut easy = generate_easy(
+            &Arc::new(GlobalOpts {
+                unix_socket: Some(socket_path),
+                ..Default::default()
+            }),
+            &None,
+        );
+        let response =
+            make_request(&mut easy, "http://localhost/ok".parse().unwrap());
+        assert_eq!(response.code, 200);
+        assert_eq!(response.content_len, 10);
+
+        let response = make_request(
+            &mut easy,
+            "http://localhost/notfound".parse().unwrap(),
+        );
+        assert_eq!(response.code, 404);
+    }
+
+    #[test]
+    fn listed_directory_metadata() {
+        let port = crate::test_server::launch();
+        let url: Url =
+            format!("http://localhost:{port}/listing").parse().unwrap();
+        let mut easy = generate_easy(&Arc::new(GlobalOpts::default()), &None);
+
+        let listed = |responses: Vec<RequestResponse>| {
+            responses
+                .into_iter()
+                .map(|response| {
+                    (
+                        response.url.path().to_string(),
+                        response.is_directory,
+                        response.listed_size,
+                        response.listed_modified,
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        let backup = (
+            String::from("/listing/backup/"),
+            true,
+            Some(4096),
+            Some(String::from("2026-10-01 09:15:00")),
+        );
+        let archive = (
+            String::from("/listing/site.tar.gz"),
+            false,
+            Some(4404019),
+            Some(String::from("2026-10-17 22:03:41")),
+        );
+
+        // Directories which are recursed into keep the metadata from the
+        // listing they were scraped from
+        let responses = listable_check(&mut easy, url.clone(), None, 0, true);
+        assert_eq!(
+            listed(responses),
+            vec![
+                (String::from("/listing/"), true, None, None),
+                backup.clone(),
+                (
+                    String::from("/listing/backup/site.sql"),
+                    false,
+                    Some(52311),
+                    Some(String::from("2026-10-01 09:14:00")),
+                ),
+                archive.clone(),
+            ]
+        );
+
+        // As do directories which are too deep to be recursed into
+        let responses = listable_check(&mut easy, url, Some(0), 0, true);
+        assert_eq!(
+            listed(responses),
+            vec![
+                (String::from("/listing/"), true, None, None),
+                backup,
+                archive
+            ]
+        );
     }
 }
@@ -15,39 +15,86 @@
 // You should have received a copy of the GNU General Public License
 // along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::{arg_parse, request, validator_thread, wordlist};
+use crate::{
+    arg_parse, bypass, drift::DriftMonitor, har, request, spider,
+    validator_thread, wordlist,
+};
 use log::{debug, trace, warn};
 use std::{
-    sync::{Arc, mpsc},
-    thread,
-    time::Duration,
+    collections::HashSet,
+    sync::{Arc, Mutex, atomic::Ordering, mpsc},
 };
 use url::Url;
 
 pub fn thread_spawn(
     dir_tx: mpsc::Sender<request::RequestResponse>,
     output_tx: mpsc::Sender<request::RequestResponse>,
-    uri_gen: wordlist::UriGenerator,
+    har_tx: har::HarSender,
+    spidered_urls: Arc<Mutex<HashSet<Url>>>,
+    mut uri_gen: wordlist::UriGenerator,
     global_opts: Arc<arg_parse::GlobalOpts>,
 ) {
     let uri = uri_gen.base.clone();
 
     debug!("Scanning {}", uri);
 
-    let mut easy = request::generate_easy(&global_opts);
+    let mut easy = request::generate_easy(&global_opts, &har_tx);
 
     let mut consecutive_errors = 0;
     let parent_index = uri_gen.parent_index;
     let parent_depth = uri_gen.parent_depth;
 
-    let validator = uri_gen.validator.clone();
+    let mut validator = uri_gen.validator.clone();
+    // Validators for the other directories that links lead to
+    let mut spider_validators = validator_thread::ValidatorCache::default();
+    spider_validators.insert(
+        uri.clone(),
+        uri_gen.prefix.clone(),
+        uri_gen.suffix.clone(),
+        validator.clone(),
+    );
+    let mut drift_monitor = DriftMonitor::new(&uri_gen, &global_opts);
+
+    // Responses are held until enough requests have been made to tell
+    // whether this is a wildcard directory, if that's being checked for
+    let mut held_responses = global_opts.wildcard_threshold.map(|_| Vec::new());
 
     // For each item in the wordlist, call the request function on it
     // Then if there is a response send it to main
-    for uri in uri_gen {
-        let mut response = request::make_request(&mut easy, uri.clone());
+    while let Some(generated) = uri_gen.next() {
+        // Stop if another thread found this to be a wildcard directory
+        if uri_gen.wildcard.load(Ordering::SeqCst) {
+            debug!("Stopping scan of wildcard directory {}", uri);
+            break;
+        }
+
+        let uri = generated.url;
+        let response = request::make_custom_request(
+            &mut easy,
+            &generated.target,
+            &[],
+            uri.clone(),
+        );
 
         let code = response.code;
+        let mut responses = Vec::new();
+
+        // Follow the links in found pages and scripts while the response
+        // is still held
+        let mut spidered = if (global_opts.spider || global_opts.js_endpoints)
+            && !response.is_directory
+            && should_send_response(&global_opts, &response, &validator)
+        {
+            spider::spider(
+                &mut easy,
+                &response,
+                &global_opts,
+                &spidered_urls,
+                &mut spider_validators,
+            )
+        } else {
+            Vec::new()
+        };
 
         // If the url is a directory, then check if it's listable
         // This may also scrape listable directories if the parameter is set
@@ -63,39 +110,131 @@ pub fn thread_spawn(
 
             let mut original_response = response_list.remove(0);
             original_response.found_from_listable = false;
-            original_response.parent_index = parent_index;
-            original_response.parent_depth = parent_depth;
-            send_response(
-                &dir_tx,
-                &output_tx,
+
+            // Nothing else has been requested since the directory's page
+            // unless it was scraped
+            if (global_opts.spider || global_opts.js_endpoints)
+                && response_list.is_empty()
+                && original_response.code == 200
+            {
+                spidered = spider::spider(
+                    &mut easy,
+                    &original_response,
+                    &global_opts,
+                    &spidered_urls,
+                    &mut spider_validators,
+                );
+            }
+
+            // Directories which are forbidden may be accessible with a
+            // variation of the request
+            if bypass::should_probe(
                 &global_opts,
-                original_response,
+                &original_response,
                 &validator,
-            );
+            ) {
+                response_list.append(&mut bypass::probe_403(
+                    &mut easy,
+                    &original_response,
+                    &global_opts,
+                    &validator,
+                ));
+            }
+
+            responses.push(original_response);
+            responses.append(&mut response_list);
+        }
+        // If it isn't a directory then just send the response to the main thread
+        else {
+            let mut bypasses =
+                if bypass::should_probe(&global_opts, &response, &validator) {
+                    bypass::probe_403(
+                        &mut easy,
+                        &response,
+                        &global_opts,
+                        &validator,
+                    )
+                } else {
+                    Vec::new()
+                };
+
+            responses.push(response);
+            responses.append(&mut bypasses);
+        }
+        responses.append(&mut spidered);
+
+        // Urls are normalised, so record the path which was actually sent
+        // when it differs, such as in raw mode
+        if generated.target != uri.as_str() {
+            responses[0].requested_target = Some(generated.target);
+        }
+
+        for response in &mut responses {
+            response.parent_index = parent_index;
+            response.parent_depth = parent_depth;
+        }
 
-            for mut scraped_response in response_list {
-                scraped_response.parent_index = parent_index;
-                scraped_response.parent_depth = parent_depth;
+        // Only the requested path counts towards the hit ratio
+        uri_gen.record_hit(
+            responses[0].is_directory
+                || should_send_response(
+                    &global_opts,
+                    &responses[0],
+                    &validator,
+                ),
+        );
+
+        match &mut held_responses {
+            Some(held_responses) => held_responses.append(&mut responses),
+            None => {
+                for response in responses {
+                    send_response(
+                        &dir_tx,
+                        &output_tx,
+                        &global_opts,
+                        response,
+                        &validator,
+                        &mut drift_monitor,
+                    );
+                }
+            }
+        }
+
+        // Each time enough requests have been made either give up on the
+        // directory, or release any held responses and check again after
+        // the next lot in case the directory starts finding every path
+        if let Some(threshold) = global_opts.wildcard_threshold
+            && uri_gen.requests() >= global_opts.wildcard_requests
+        {
+            if uri_gen.hit_percentage() >= u64::from(threshold) {
+                report_wildcard(&output_tx, &uri_gen);
+                held_responses = None;
+                break;
+            }
+            uri_gen.reset_hits();
+            for response in held_responses.take().into_iter().flatten() {
                 send_response(
                     &dir_tx,
                     &output_tx,
                     &global_opts,
-                    scraped_response,
+                    response,
                     &validator,
+                    &mut drift_monitor,
                 );
             }
         }
-        // If it isn't a directory then just send the response to the main thread
-        else {
-            response.parent_index = parent_index;
-            response.parent_depth = parent_depth;
-            send_response(
-                &dir_tx,
-                &output_tx,
-                &global_opts,
-                response,
-                &validator,
-            );
+
+        // Check whether the not found responses have changed
+        if let Some(monitor) = &mut drift_monitor
+            && monitor.tick()
+        {
+            for mut checked_response in
+                monitor.check(&mut easy, &global_opts, &mut validator)
+            {
+                checked_response.parent_index = parent_index;
+                checked_response.parent_depth = parent_depth;
+                output_tx.send(checked_response).unwrap();
+            }
         }
 
         // Detect consecutive errors and stop the thread if the count is exceeded
@@ -116,10 +255,32 @@ pub fn thread_spawn(
         }
 
         // Sleep if throttle is set
-        if global_opts.throttle != 0 {
-            thread::sleep(Duration::from_millis(u64::from(
-                global_opts.throttle,
-            )));
+        request::throttle(&global_opts);
+    }
+
+    // Release any responses held if there weren't enough words to check
+    // for a wildcard directory, unless another thread found it to be one
+    if !uri_gen.wildcard.load(Ordering::SeqCst) {
+        for response in held_responses.into_iter().flatten() {
+            send_response(
+                &dir_tx,
+                &output_tx,
+                &global_opts,
+                response,
+                &validator,
+                &mut drift_monitor,
+            );
+        }
+    }
+
+    // Confirm any hits still being held
+    if let Some(monitor) = &mut drift_monitor {
+        for mut checked_response in
+            monitor.check(&mut easy, &global_opts, &mut validator)
+        {
+            checked_response.parent_index = parent_index;
+            checked_response.parent_depth = parent_depth;
+            output_tx.send(checked_response).unwrap();
         }
     }
 
@@ -129,6 +290,35 @@ pub fn thread_spawn(
     dir_tx.send(generate_end()).unwrap();
 }
 
+// Reports the directory as a wildcard directory, unless another thread
+// scanning it with the same prefix and extension already has
+fn report_wildcard(
+    output_tx: &mpsc::Sender<request::RequestResponse>,
+    uri_gen: &wordlist::UriGenerator,
+) {
+    if uri_gen.wildcard.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    warn!(
+        "{} is a wildcard directory, {}% of paths with prefix \"{}\" and \
+        suffix \"{}\" were found",
+        uri_gen.base,
+        uri_gen.hit_percentage(),
+        uri_gen.prefix,
+        uri_gen.suffix
+    );
+    let mut wildcard =
+        request::fabricate_request_response(uri_gen.base.clone(), false, false);
+    wildcard.found_from_listable = false;
+    wildcard.wildcard_directory = true;
+    wildcard.source =
+        format!("{} of {} paths found", uri_gen.hits(), uri_gen.requests());
+    wildcard.parent_index = uri_gen.parent_index;
+    wildcard.parent_depth = uri_gen.parent_depth;
+    output_tx.send(wildcard).unwrap();
+}
+
 // Sends the given RequestResponse to the main thread
 // dependent on whitelist/blacklist settings and response code
 #[inline]
@@ -138,14 +328,32 @@ fn send_response(
     global_opts: &arg_parse::GlobalOpts,
     response: request::RequestResponse,
     validator_opt: &Option<validator_thread::TargetValidator>,
+    drift_monitor: &mut Option<DriftMonitor>,
 ) {
     if response.is_directory {
+        // Directories matching the user's not found rules are skipped,
+        // there's nothing else to tell that they don't exist
+        if response.not_found_rule {
+            trace!("[{}]: matches a user not found rule", response.url);
+            return;
+        }
         dir_tx.send(response.clone()).unwrap();
         output_tx.send(response).unwrap();
         return;
     }
-    if should_send_response(global_opts, &response, validator_opt) {
+    // Files scraped from listings weren't requested so have no response
+    // to check
+    if response.found_from_listable {
         output_tx.send(response).unwrap();
+        return;
+    }
+    if should_send_response(global_opts, &response, validator_opt) {
+        // Hits are held until the not found responses have been checked
+        // again if that's enabled
+        match drift_monitor {
+            Some(monitor) => monitor.hold(response),
+            None => output_tx.send(response).unwrap(),
+        }
     }
 }
 
@@ -174,11 +382,35 @@ pub fn should_send_response(
         trace!("[{}]: code 0 detected", response.url);
         return false;
     }
-    if let Some(validator) = validator_opt {
-        if validator.is_not_found(response) {
-            trace!("[{}]: matches Not Found condition", response.url);
-            return false;
-        }
+    if response.not_found_rule {
+        trace!("[{}]: matches a user not found rule", response.url);
+        return false;
+    }
+    if let Some(validator) = validator_opt
+        && validator.is_not_found(response)
+    {
+        trace!("[{}]: matches Not Found condition", response.url);
+        return false;
+    }
+
+    // Check that the response time is within the requested bounds
+    if let Some(min_time) = global_opts.min_time
+        && response.total_time < min_time
+    {
+        trace!(
+            "[{}]: time {}ms is below the minimum",
+            response.url, response.total_time
+        );
+        return false;
+    }
+    if let Some(max_time) = global_opts.max_time
+        && response.total_time > max_time
+    {
+        trace!(
+            "[{}]: time {}ms is above the maximum",
+            response.url, response.total_time
+        );
+        return false;
     }
 
     // Check that the response size has not been blacklisted
@@ -196,28 +428,21 @@ pub fn should_send_response(
 
 #[inline]
 fn generate_end() -> request::RequestResponse {
-    request::RequestResponse {
-        url: Url::parse("data:END").unwrap(),
-        code: 0,
-        content_len: 0,
-        is_directory: false,
-        is_listable: false,
-        redirect_url: String::from(""),
-        found_from_listable: false,
-        parent_index: 0,
-        parent_depth: 0,
-    }
+    request::RequestResponse::new(Url::parse("data:END").unwrap())
 }
 
 #[cfg(test)]
 mod test {
 
     use crate::{
-        arg_parse::{GlobalOpts, LengthRange, LengthRanges},
-        request::RequestResponse,
-        request_thread::should_send_response,
+        arg_parse::{GlobalOpts, LengthRange, LengthRanges, PathMode},
+        request::{self, RequestResponse},
+        request_thread::{send_response, should_send_response, thread_spawn},
         validator_thread::TargetValidator,
+        wordlist::UriGenerator,
     };
+    use std::sync::{Arc, mpsc};
+    use url::Url;
 
     #[test]
     fn test_should_send_response() {
@@ -295,5 +520,231 @@ mod test {
             should_send_response(&globalopts, &rr, &None),
             "Length outside blacklist range failed"
         );
+
+        // Response time is below the minimum -> false
+        rr.total_time = 50;
+        globalopts.min_time = Some(100);
+        assert!(
+            !should_send_response(&globalopts, &rr, &None),
+            "Time below minimum failed"
+        );
+
+        // Response time is above the maximum -> false
+        rr.total_time = 500;
+        globalopts.max_time = Some(400);
+        assert!(
+            !should_send_response(&globalopts, &rr, &None),
+            "Time above maximum failed"
+        );
+
+        // Response time is within the bounds -> true
+        rr.total_time = 200;
+        assert!(
+            should_send_response(&globalopts, &rr, &None),
+            "Time within bounds failed"
+        );
+    }
+
+    #[test]
+    fn test_directory_not_found_rule() {
+        let global_opts = GlobalOpts::default();
+        let directory = RequestResponse {
+            is_directory: true,
+            ..Default::default()
+        };
+
+        // Directories are passed on to be scanned unless they match a rule
+        for not_found_rule in [false, true] {
+            let (dir_tx, dir_rx) = mpsc::channel();
+            let (output_tx, output_rx) = mpsc::channel();
+            send_response(
+                &dir_tx,
+                &output_tx,
+                &global_opts,
+                RequestResponse {
+                    not_found_rule,
+                    ..directory.clone()
+                },
+                &None,
+                &mut None,
+            );
+            assert_eq!(dir_rx.try_iter().count(), usize::from(!not_found_rule));
+            assert_eq!(
+                output_rx.try_iter().count(),
+                usize::from(!not_found_rule)
+            );
+        }
+    }
+
+    #[test]
+    fn test_scraped_file_sent() {
+        let (dir_tx, _dir_rx) = mpsc::channel();
+        let (output_tx, output_rx) = mpsc::channel();
+        let scraped = request::fabricate_request_response(
+            Url::parse("http://example.com/listed.txt").unwrap(),
+            false,
+            false,
+        );
+
+        // Scraped files have no response code, but are still reported
+        send_response(
+            &dir_tx,
+            &output_tx,
+            &GlobalOpts::default(),
+            scraped,
+            &None,
+            &mut None,
+        );
+        let output = output_rx.try_iter().collect::<Vec<_>>();
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].code, 0);
+        assert!(output[0].found_from_listable);
+    }
+
+    #[test]
+    fn test_wildcard_directory() {
+        let port = crate::test_server::launch();
+        let base = Url::parse(&format!("http://localhost:{port}/")).unwrap();
+        let global_opts = Arc::new(GlobalOpts {
+            code_list: vec![404],
+            wildcard_threshold: Some(90),
+            wildcard_requests: 5,
+            ..Default::default()
+        });
+
+        // Every .php path is found without a validator
+        let scan = |suffix: &str| {
+            let words = (0..20).map(|i| format!("word{i}")).collect();
+            let uri_gen = UriGenerator::new(
+                base.clone(),
+                String::new(),
+                suffix.into(),
+                Arc::new(words),
+                0,
+                1,
+                0,
+                0,
+                None,
+                false,
+                PathMode::Normal,
+                false,
+            );
+            let (dir_tx, dir_rx) = mpsc::channel();
+            let (output_tx, output_rx) = mpsc::channel();
+            thread_spawn(
+                dir_tx,
+                output_tx,
+                None,
+                Default::default(),
+                uri_gen,
+                global_opts.clone(),
+            );
+            assert_eq!(dir_rx.try_iter().count(), 1);
+            output_rx.try_iter().collect::<Vec<_>>()
+        };
+
+        let output = scan(".php");
+        assert_eq!(output.len(), 1);
+        assert!(output[0].wildcard_directory);
+        assert_eq!(output[0].url, base);
+        assert_eq!(output[0].source, "5 of 5 paths found");
+
+        // Nothing is found in other directories
+        assert!(scan(".txt").is_empty());
+    }
+
+    #[test]
+    fn test_later_wildcard_directory() {
+        let port = crate::test_server::launch();
+        let base = Url::parse(&format!("http://localhost:{port}/")).unwrap();
+        let global_opts = Arc::new(GlobalOpts {
+            code_list: vec![404],
+            wildcard_threshold: Some(90),
+            wildcard_requests: 5,
+            ..Default::default()
+        });
+
+        // Only the words after the first lot of requests are found
+        let words = (0..20)
+            .map(|i| match i {
+                0..5 => format!("word{i}"),
+                _ => format!("word{i}.php"),
+            })
+            .collect();
+        let uri_gen = UriGenerator::new(
+            base.clone(),
+            String::new(),
+            String::new(),
+            Arc::new(words),
+            0,
+            1,
+            0,
+            0,
+            None,
+            false,
+            PathMode::Normal,
+            false,
+        );
+        let (dir_tx, _dir_rx) = mpsc::channel();
+        let (output_tx, output_rx) = mpsc::channel();
+        thread_spawn(
+            dir_tx,
+            output_tx,
+            None,
+            Default::default(),
+            uri_gen,
+            global_opts,
+        );
+
+        // The scan stops once the next lot of requests are all found
+        let output = output_rx.try_iter().collect::<Vec<_>>();
+        assert_eq!(output.len(), 6);
+        assert!(output[5].wildcard_directory);
+        assert_eq!(output[5].source, "5 of 5 paths found");
+    }
+
+    #[test]
+    fn test_raw_path_reported() {
+        let port = crate::test_server::launch();
+        let base =
+            Url::parse(&format!("http://localhost:{port}/pages/")).unwrap();
+        let global_opts = Arc::new(GlobalOpts {
+            code_list: vec![404],
+            path_mode: PathMode::Raw,
+            ..Default::default()
+        });
+
+        let uri_gen = UriGenerator::new(
+            base.clone(),
+            String::new(),
+            ".php".into(),
+            Arc::new(vec!["../x".into()]),
+            0,
+            1,
+            0,
+            0,
+            None,
+            false,
+            PathMode::Raw,
+            false,
+        );
+        let (dir_tx, dir_rx) = mpsc::channel();
+        let (output_tx, output_rx) = mpsc::channel();
+        thread_spawn(
+            dir_tx,
+            output_tx,
+            None,
+            Default::default(),
+            uri_gen,
+            global_opts,
+        );
+        assert_eq!(dir_rx.try_iter().count(), 1);
+
+        // The path is sent without removing the dot segments
+        let output = output_rx.try_iter().collect::<Vec<_>>();
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].url, base.join("../x.php").unwrap());
+        assert_eq!(output[0].requested_target, Some(format!("{base}../x.php")));
+        assert!(output[0].source_url.is_empty());
     }
 }
@@ -0,0 +1,337 @@
+// This file is part of Dirble - https://www.github.com/nccgroup/dirble
+// Copyright (C) 2019 Izzy Whistlecroft <Izzy(dot)Whistlecroft(at)nccgroup(dot)com>
+// Released as open source by NCC Group Plc - https://www.nccgroup.com/
+//
+// Dirble is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Dirble is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    arg_parse::GlobalOpts,
+    content_parse, har,
+    request::{self, RequestResponse},
+    request_thread,
+    validator_thread::{DirectoryInfo, ValidatorCache},
+};
+use flate2::read::GzDecoder;
+use log::{debug, info};
+use percent_encoding::percent_decode_str;
+use std::{
+    collections::{HashSet, VecDeque},
+    io::Read,
+    sync::{Arc, mpsc},
+};
+use url::Url;
+
+// The most sitemaps which are fetched for a host, as sitemap indexes can
+// list a very large number of them
+const MAX_SITEMAPS: usize = 50;
+
+// Paths found in the robots.txt and sitemaps of a host
+pub struct Seeds {
+    // The responses for the paths which were found
+    pub responses: Vec<RequestResponse>,
+    // The words from all of the paths, to be added to the wordlist
+    pub words: Vec<String>,
+}
+
+// Seeds the scan of a host, sending the host back to main with the words
+// that were found so that it can be scanned with them. The paths which
+// were found are then sent on like those found by a request thread.
+pub fn seed_thread(
+    dir_info: DirectoryInfo,
+    main_tx: mpsc::Sender<(DirectoryInfo, Vec<String>)>,
+    dir_tx: mpsc::Sender<RequestResponse>,
+    output_tx: mpsc::Sender<RequestResponse>,
+    har_tx: har::HarSender,
+    global_opts: Arc<GlobalOpts>,
+) {
+    let seeds = seed(&dir_info, &global_opts, &har_tx);
+    let (parent_index, parent_depth) =
+        (dir_info.parent_index, dir_info.parent_depth);
+    main_tx.send((dir_info, seeds.words)).unwrap();
+
+    for mut response in seeds.responses {
+        response.parent_index = parent_index;
+        response.parent_depth = parent_depth;
+        if response.is_directory {
+            dir_tx.send(response.clone()).unwrap();
+        }
+        output_tx.send(response).unwrap();
+    }
+
+    // Let main know that the thread is done once the directories have
+    // been validated
+    dir_tx
+        .send(RequestResponse::new(Url::parse("data:END").unwrap()))
+        .unwrap();
+}
+
+// Fetches the robots.txt and sitemaps of the host and requests each of
+// the paths listed in them which are under the host, along with their
+// parent directories
+pub fn seed(
+    dir_info: &DirectoryInfo,
+    global_opts: &Arc<GlobalOpts>,
+    har_tx: &har::HarSender,
+) -> Seeds {
+    let host = &dir_info.url;
+    let mut easy = request::generate_easy(global_opts, har_tx);
+    // The paths are checked with the validator for their own directory
+    // and suffix, the ones for the host are already known
+    let mut validators = ValidatorCache::default();
+    validators.insert(
+        host.clone(),
+        String::new(),
+        String::new(),
+        dir_info.validator.clone(),
+    );
+    for ((prefix, extension), validator) in &dir_info.suffix_validators {
+        validators.insert(
+            host.clone(),
+            prefix.clone(),
+            extension.clone(),
+            Some(validator.clone()),
+        );
+    }
+    // Each path along with the name and url of the file it was found in
+    let mut paths: Vec<(Url, &str, Url)> = Vec::new();
+    let mut sitemaps = VecDeque::from([host.join("/sitemap.xml").unwrap()]);
+
+    let robots_url = host.join("/robots.txt").unwrap();
+    let robots = request::make_request(&mut easy, robots_url.clone());
+    request::throttle(global_opts);
+    if robots.code == 200 {
+        let content = String::from_utf8_lossy(easy.get_ref().contents());
+        let (robots_paths, robots_sitemaps) =
+            content_parse::robots_entries(&content);
+        for path in robots_paths {
+            if let Ok(url) = host.join(&path) {
+                paths.push((url, "robots.txt", robots_url.clone()));
+            }
+        }
+        sitemaps.extend(
+            robots_sitemaps
+                .iter()
+                .filter_map(|sitemap| host.join(sitemap).ok()),
+        );
+    }
+
+    // Sitemap indexes list further sitemaps, which may be compressed
+    let mut fetched_sitemaps = HashSet::new();
+    while let Some(sitemap_url) = sitemaps.pop_front() {
+        if fetched_sitemaps.len() >= MAX_SITEMAPS
+            || !fetched_sitemaps.insert(sitemap_url.clone())
+        {
+            continue;
+        }
+        let sitemap = request::make_request(&mut easy, sitemap_url.clone());
+        request::throttle(global_opts);
+        if sitemap.code != 200 {
+            continue;
+        }
+
+        let content = decompress(easy.get_ref().contents());
+        let (indexed_sitemaps, pages) =
+            content_parse::sitemap_entries(&content);
+        sitemaps.extend(
+            indexed_sitemaps
+                .iter()
+                .filter_map(|sitemap| host.join(sitemap).ok()),
+        );
+        for page in pages {
+            if let Ok(url) = host.join(&page) {
+                paths.push((url, "sitemap", sitemap_url.clone()));
+            }
+        }
+    }
+    info!(
+        "Found {} paths in robots.txt and sitemaps of {}",
+        paths.len(),
+        host
+    );
+
+    let mut requested = HashSet::new();
+    let mut responses = Vec::new();
+    let mut words = Vec::new();
+    for (mut url, source, source_url) in paths {
+        url.set_query(None);
+        url.set_fragment(None);
+        if !url.as_str().starts_with(host.as_str()) {
+            continue;
+        }
+
+        for word in path_words(host, &url) {
+            if !words.contains(&word) {
+                words.push(word);
+            }
+        }
+
+        // The directories the path is in are requested before it
+        for target in parent_directories(host, &url).into_iter().chain([url]) {
+            if !requested.insert(target.clone()) {
+                continue;
+            }
+
+            let validator = validators.get(&target, &mut easy, global_opts);
+            let mut response = request::make_request(&mut easy, target.clone());
+            request::throttle(global_opts);
+            if !request_thread::should_send_response(
+                global_opts,
+                &response,
+                &validator,
+            ) {
+                debug!("{} from {} was not found", target, source);
+                continue;
+            }
+            response.is_directory =
+                request::is_directory(&mut easy, &target, global_opts);
+            response.source = String::from(source);
+            response.source_url = source_url.to_string();
+            responses.push(response);
+        }
+    }
+
+    Seeds { responses, words }
+}
+
+// Returns the words in the path of the url under the host. Extensions
+// are added to words when scanning, so they're removed from filenames.
+fn path_words(host: &Url, url: &Url) -> Vec<String> {
+    let path = &url.as_str()[host.as_str().len()..];
+    let mut words: Vec<String> = path
+        .split('/')
+        .filter(|word| !word.is_empty())
+        .map(|word| percent_decode_str(word).decode_utf8_lossy().into_owned())
+        .collect();
+
+    if !path.ends_with('/')
+        && let Some(filename) = words.last_mut()
+        && let Some((stem, _)) = filename.rsplit_once('.')
+        && !stem.is_empty()
+    {
+        *filename = stem.to_string();
+    }
+    words
+}
+
+// Returns the directories between the host and the url
+fn parent_directories(host: &Url, url: &Url) -> Vec<Url> {
+    let mut directories = Vec::new();
+    let mut directory = url.join(".").unwrap();
+    while directory.as_str().len() > host.as_str().len()
+        && directory.as_str().starts_with(host.as_str())
+    {
+        directories.push(directory.clone());
+        directory = directory.join("..").unwrap();
+    }
+    directories.reverse();
+    directories
+}
+
+// Sitemaps are often served gzipped without a content encoding
+fn decompress(content: &[u8]) -> String {
+    if content.starts_with(&[0x1f, 0x8b]) {
+        let mut decompressed = String::new();
+        if GzDecoder::new(content)
+            .read_to_string(&mut decompressed)
+            .is_ok()
+        {
+            return decompressed;
+        }
+    }
+    String::from_utf8_lossy(content).into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_seed() {
+        let port = crate::test_server::launch();
+        let host = Url::parse(&format!("http://localhost:{port}/")).unwrap();
+        let global_opts = Arc::new(GlobalOpts {
+            hostnames: vec![host.clone()],
+            code_list: vec![404],
+            ..Default::default()
+        });
+
+        let dir_info = DirectoryInfo::new(host.clone(), None, 0, 0);
+        let seeds = seed(&dir_info, &global_opts, &None);
+
+        let found = seeds
+            .responses
+            .iter()
+            .map(|response| (response.url.path(), response.source.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![
+                ("/secret/admin.php", "robots.txt"),
+                ("/ok", "robots.txt"),
+                ("/pages/index.html", "sitemap"),
+                ("/pages/docs/", "sitemap"),
+                ("/201", "sitemap"),
+                ("/search/", "sitemap"),
+            ]
+        );
+        assert!(seeds.responses[3].is_directory);
+        // Paths with a slash which the path without one doesn't redirect
+        // to aren't scanned as directories
+        assert!(!seeds.responses[5].is_directory);
+        assert_eq!(
+            seeds.responses[2].source_url,
+            host.join("sitemap-pages.xml.gz").unwrap().as_str()
+        );
+        assert_eq!(
+            seeds.words,
+            vec![
+                "secret", "admin", "ok", "pages", "index", "docs", "201",
+                "search"
+            ]
+        );
+
+        // Nonexistent .php files are recognised with their own validator
+        let global_opts = Arc::new(GlobalOpts {
+            extensions: vec!["".into(), ".php".into()],
+            ..(*global_opts).clone()
+        });
+        let seeds = seed(&dir_info, &global_opts, &None);
+        assert!(
+            seeds
+                .responses
+                .iter()
+                .all(|response| response.url.path() != "/secret/admin.php")
+        );
+    }
+
+    #[test]
+    fn test_path_words() {
+        let host = Url::parse("http://example.com/app/").unwrap();
+        let words = |path| path_words(&host, &host.join(path).unwrap());
+        assert_eq!(words("a%20b/c.d/e.tar.gz"), vec!["a b", "c.d", "e.tar"]);
+        assert_eq!(words("v1.2/"), vec!["v1.2"]);
+        assert_eq!(words(".htaccess"), vec![".htaccess"]);
+    }
+
+    #[test]
+    fn test_parent_directories() {
+        let host = Url::parse("http://example.com/app/").unwrap();
+        let url = host.join("a/b/c.html").unwrap();
+        assert_eq!(
+            parent_directories(&host, &url),
+            vec![host.join("a/").unwrap(), host.join("a/b/").unwrap()]
+        );
+        assert!(parent_directories(&host, &host.join("d").unwrap()).is_empty());
+    }
+}
@@ -0,0 +1,303 @@
+// This file is part of Dirble - https://www.github.com/nccgroup/dirble
+// Copyright (C) 2019 Izzy Whistlecroft <Izzy(dot)Whistlecroft(at)nccgroup(dot)com>
+// Released as open source by NCC Group Plc - https://www.nccgroup.com/
+//
+// Dirble is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Dirble is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    arg_parse::GlobalOpts,
+    content_parse,
+    request::{self, Collector, RequestResponse},
+    request_thread,
+    validator_thread::ValidatorCache,
+};
+use curl::easy::Easy2;
+use log::trace;
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::Mutex,
+};
+use url::Url;
+
+// A url found in a page or script, along with where it was found and how
+// many links away from the page found by the scan it is
+struct Link {
+    url: Url,
+    from: Url,
+    source: &'static str,
+    depth: u32,
+}
+
+// Follows the links in the page or script which was just requested with
+// the given easy, requesting each in scope url it links to and following
+// the links in those in turn, up to the spider depth. Urls which have
+// already been spidered during the scan are skipped. Returns the
+// responses which were found, with directories marked so that they're
+// scanned.
+pub fn spider(
+    easy: &mut Easy2<Collector>,
+    page: &RequestResponse,
+    global_opts: &GlobalOpts,
+    spidered: &Mutex<HashSet<Url>>,
+    validators: &mut ValidatorCache,
+) -> Vec<RequestResponse> {
+    let mut output = Vec::new();
+
+    spidered.lock().unwrap().insert(page.url.clone());
+    let mut queue: VecDeque<Link> =
+        page_links(easy, &page.url, &page.url, 1, global_opts).into();
+
+    while let Some(link) = queue.pop_front() {
+        if !in_scope(&link.url, global_opts)
+            || !spidered.lock().unwrap().insert(link.url.clone())
+        {
+            continue;
+        }
+
+        trace!("Spidering {} from {}", link.url, link.from);
+        // These make requests, so are done before the link is requested
+        // to leave its page in the easy
+        let validator = validators.get(&link.url, easy, global_opts);
+        let is_directory = request::is_directory(easy, &link.url, global_opts);
+
+        let mut response = request::make_request(easy, link.url.clone());
+        request::throttle(global_opts);
+        if !request_thread::should_send_response(
+            global_opts,
+            &response,
+            &validator,
+        ) {
+            continue;
+        }
+
+        response.is_directory = is_directory;
+        if link.depth < global_opts.spider_depth {
+            // Paths in scripts are relative to the page which loaded them
+            queue.extend(page_links(
+                easy,
+                &link.url,
+                &link.from,
+                link.depth + 1,
+                global_opts,
+            ));
+        }
+
+        response.source = String::from(link.source);
+        response.source_url = link.from.to_string();
+        output.push(response);
+    }
+
+    output
+}
+
+// Returns the urls linked to by the response currently held by the
+// easy, if it's an HTML page or a script which is being followed. Paths
+// in scripts are resolved against the given page.
+fn page_links(
+    easy: &mut Easy2<Collector>,
+    url: &Url,
+    page_url: &Url,
+    depth: u32,
+    global_opts: &GlobalOpts,
+) -> Vec<Link> {
+    let content_type = easy
+        .content_type()
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+        .to_lowercase();
+    let content = String::from_utf8_lossy(easy.get_ref().contents());
+
+    if global_opts.spider && content_type.starts_with("text/html") {
+        content_parse::spider_urls(&content, url)
+            .into_iter()
+            .map(|link| Link {
+                url: link,
+                from: url.clone(),
+                source: "spider",
+                depth,
+            })
+            .collect()
+    } else if global_opts.js_endpoints
+        && (content_type.contains("javascript")
+            || url.path().ends_with(".js")
+            || url.path().ends_with(".mjs"))
+    {
+        content_parse::js_endpoints(&content, page_url)
+            .into_iter()
+            .map(|link| Link {
+                url: link,
+                from: url.clone(),
+                source: "javascript",
+                depth,
+            })
+            .collect()
+    } else {
+        Vec::new()
+    }
+}
+
+// Only urls under one of the hosts being scanned are spidered. The paths
+// are compared by segment so that /app doesn't include /application.
+fn in_scope(url: &Url, global_opts: &GlobalOpts) -> bool {
+    global_opts.hostnames.iter().any(|host| {
+        if url.origin() != host.origin() {
+            return false;
+        }
+        let mut segments = url.path_segments().into_iter().flatten();
+        host.path_segments()
+            .into_iter()
+            .flatten()
+            .filter(|segment| !segment.is_empty())
+            .all(|segment| segments.next() == Some(segment))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_spider() {
+        let port = crate::test_server::launch();
+        let base = Url::parse(&format!("http://localhost:{port}/")).unwrap();
+        let index = base.join("pages/index.html").unwrap();
+        let about = base.join("pages/about.html").unwrap();
+
+        let spider_index = |global_opts: GlobalOpts| {
+            let global_opts = GlobalOpts {
+                hostnames: vec![base.clone()],
+                code_list: vec![404],
+                spider: true,
+                ..global_opts
+            };
+            let mut easy =
+                request::generate_easy(&global_opts.clone().into(), &None);
+            let page = request::make_request(&mut easy, index.clone());
+            let output = spider(
+                &mut easy,
+                &page,
+                &global_opts,
+                &Mutex::new(HashSet::new()),
+                &mut ValidatorCache::default(),
+            );
+            assert!(output.iter().all(|response| response.source == "spider"));
+            output
+                .iter()
+                .map(|response| {
+                    (
+                        response.url.path().to_string(),
+                        response.is_directory,
+                        response.source_url.clone(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        let found = |path: &str, is_directory, page: &Url| {
+            (path.to_string(), is_directory, page.to_string())
+        };
+
+        // Nonexistent .php files are only recognised with their own
+        // validator, and /search/ doesn't redirect from /search
+        assert_eq!(
+            spider_index(GlobalOpts {
+                extensions: vec!["".into(), ".php".into()],
+                ..Default::default()
+            }),
+            vec![
+                found("/pages/about.html", false, &index),
+                found("/ok", false, &index),
+                found("/pages/docs/", true, &index),
+                found("/search/", false, &about),
+            ]
+        );
+        assert!(spider_index(Default::default()).contains(&found(
+            "/pages/missing.php",
+            false,
+            &about
+        )));
+
+        // Links in the pages linked to aren't followed past the depth
+        assert_eq!(
+            spider_index(GlobalOpts {
+                spider_depth: 1,
+                ..Default::default()
+            }),
+            vec![
+                found("/pages/about.html", false, &index),
+                found("/ok", false, &index),
+                found("/pages/docs/", true, &index),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_in_scope() {
+        let global_opts = GlobalOpts {
+            hostnames: vec!["http://example.com/app/".parse().unwrap()],
+            ..Default::default()
+        };
+        let in_scope =
+            |url: &str| in_scope(&url.parse().unwrap(), &global_opts);
+
+        assert!(in_scope("http://example.com/app"));
+        assert!(in_scope("http://example.com/app/admin/"));
+        assert!(!in_scope("http://example.com/application"));
+        assert!(!in_scope("http://example.com/"));
+        assert!(!in_scope("http://example.com:8080/app/"));
+        assert!(!in_scope("https://example.com/app/"));
+    }
+
+    #[test]
+    fn test_js_endpoints() {
+        let port = crate::test_server::launch();
+        let base = Url::parse(&format!("http://localhost:{port}/")).unwrap();
+        let global_opts = GlobalOpts {
+            hostnames: vec![base.clone()],
+            code_list: vec![404],
+            js_endpoints: true,
+            ..Default::default()
+        };
+        let mut easy =
+            request::generate_easy(&global_opts.clone().into(), &None);
+
+        let script = base.join("pages/app.js").unwrap();
+        let page = request::make_request(&mut easy, script.clone());
+        let output = spider(
+            &mut easy,
+            &page,
+            &global_opts,
+            &Mutex::new(HashSet::new()),
+            &mut ValidatorCache::default(),
+        );
+
+        let found = output
+            .iter()
+            .map(|response| {
+                (
+                    response.url.path(),
+                    response.source.as_str(),
+                    response.source_url.as_str(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![
+                ("/ok", "javascript", script.as_str()),
+                ("/pages/about.html", "javascript", script.as_str()),
+            ]
+        );
+    }
+}
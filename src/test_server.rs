@@ -1,5 +1,5 @@
-use axum::{Router, extract::Path};
-use http::StatusCode;
+use axum::{Router, extract::Path, response::Html};
+use http::{HeaderMap, StatusCode, header};
 use tokio::net::TcpListener;
 
 pub const PATHS: phf::Map<&str, TestPath> = phf::phf_map! {
@@ -13,6 +13,84 @@ pub const PATHS: phf::Map<&str, TestPath> = phf::phf_map! {
     },
 };
 
+// HTML pages which link to each other, for spidering
+const INDEX_PAGE: &str = r#"<html><head>
+<link rel="stylesheet" href="style.css">
+<script src="/ok"></script>
+</head><body>
+<a href="about.html">About</a>
+<a href="https://elsewhere.example/">Elsewhere</a>
+<img src="missing.png">
+<form action="docs/?q=search"></form>
+</body></html>"#;
+
+const ABOUT_PAGE: &str = r#"<html><body>
+<a href="index.html#top">Home</a>
+<a href="/search/">Search</a>
+<a href="missing.php">Missing</a>
+<iframe src="/ok"></iframe>
+</body></html>"#;
+
+// A Jetty directory listing, which gives sizes and times of directories
+const LISTING: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head><title>Directory: /listing/</title></head>
+<body>
+<h1 class="title">Directory: /listing/</h1>
+<table class="listing">
+<tbody>
+<tr><td class="name"><a href="/listing/../">Parent Directory</a></td><td class="lastmodified">-</td><td class="size">-</td></tr>
+<tr><td class="name"><a href="/listing/backup/">backup/&nbsp;</a></td><td class="lastmodified">Oct 1, 2026, 9:15:00 AM UTC</td><td class="size">4,096 bytes&nbsp;</td></tr>
+<tr><td class="name"><a href="/listing/site.tar.gz">site.tar.gz&nbsp;</a></td><td class="lastmodified">Oct 17, 2026, 10:03:41 PM UTC</td><td class="size">4,404,019 bytes&nbsp;</td></tr>
+</tbody>
+</table>
+</body></html>"#;
+
+const BACKUP_LISTING: &str = r#"<html>
+<head><title>Index of /listing/backup/</title></head>
+<body>
+<h1>Index of /listing/backup/</h1><hr><pre><a href="../">../</a>
+<a href="site.sql">site.sql</a>                                           01-Oct-2026 09:14               52311
+</pre><hr></body>
+</html>"#;
+
+// A script with paths in its string literals
+const APP_SCRIPT: &str = r#"fetch("/ok").then(r => r.text());
+import('./about.html');
+const headers = { "Content-Type": "text/html" };
+const api = `/api/users?id=${id}`;
+"#;
+
+const ROBOTS: &str = "User-agent: *
+Disallow: /secret/admin.php
+Allow: /ok # Comment
+Disallow: /*.bak$
+Sitemap: /sitemap_index.xml
+";
+
+const SITEMAP_INDEX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <sitemap><loc>/sitemap-pages.xml.gz</loc></sitemap>
+</sitemapindex>"#;
+
+const SITEMAP: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <url><loc>/pages/index.html</loc></url>
+  <url><loc>/pages/docs/</loc></url>
+  <url><loc>/201?page=2</loc></url>
+  <url><loc>/search/</loc></url>
+  <url><loc>https://elsewhere.example/</loc></url>
+</urlset>"#;
+
+async fn gzipped_sitemap() -> Vec<u8> {
+    use flate2::{Compression, write::GzEncoder};
+    use std::io::Write;
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(SITEMAP.as_bytes()).unwrap();
+    encoder.finish().unwrap()
+}
+
 pub struct TestPath {
     pub code: StatusCode,
     pub length: usize,
@@ -23,6 +101,29 @@ pub fn launch() -> u16 {
     listener.set_nonblocking(true).unwrap();
 
     let port = listener.local_addr().unwrap().port();
+
+    spawn_server(async move { TcpListener::from_std(listener).unwrap() });
+
+    port
+}
+
+// Serves the test paths on a Unix domain socket at the given path
+#[cfg(unix)]
+pub fn launch_unix(path: &std::path::Path) {
+    let listener = std::os::unix::net::UnixListener::bind(path).unwrap();
+    listener.set_nonblocking(true).unwrap();
+
+    spawn_server(async move {
+        tokio::net::UnixListener::from_std(listener).unwrap()
+    });
+}
+
+fn spawn_server<L, F>(make_listener: F)
+where
+    L: axum::serve::Listener,
+    L::Addr: std::fmt::Debug,
+    F: Future<Output = L> + Send + 'static,
+{
     let app = make_router();
 
     std::thread::spawn(move || {
@@ -31,26 +132,89 @@ pub fn launch() -> u16 {
             .build()
             .expect("Failed to create tokio runtime")
             .block_on(async {
-                let listener = TcpListener::from_std(listener).unwrap();
+                let listener = make_listener.await;
 
                 axum::serve(listener, app).await.unwrap();
             })
     });
-
-    port
 }
 
 fn make_router() -> Router {
     use axum::routing::get;
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    let varying_requests = Arc::new(AtomicUsize::new(0));
 
     Router::new()
         .route("/", get(|| async { "OK" }))
+        .route("/pages/index.html", get(|| async { Html(INDEX_PAGE) }))
+        .route("/pages/about.html", get(|| async { Html(ABOUT_PAGE) }))
+        .route("/pages/docs/", get(|| async { "Docs" }))
+        .route(
+            "/pages/docs",
+            get(|| async {
+                (
+                    StatusCode::MOVED_PERMANENTLY,
+                    [(header::LOCATION, "/pages/docs/")],
+                )
+            }),
+        )
+        // Found with a trailing slash, but isn't a directory
+        .route("/search/", get(|| async { "Search" }))
+        .route("/robots.txt", get(|| async { ROBOTS }))
+        .route("/listing/", get(|| async { Html(LISTING) }))
+        .route("/listing/backup/", get(|| async { Html(BACKUP_LISTING) }))
+        // Paths on a case insensitive part of the server
+        .route("/files/", get(|| async { "Files" }))
+        .route("/FILES/", get(|| async { "Files" }))
+        .route("/index.html", get(|| async { "Index" }))
+        .route("/INDEX.HTML", get(|| async { "Index" }))
+        .route("/sitemap_index.xml", get(|| async { SITEMAP_INDEX }))
+        .route("/sitemap-pages.xml.gz", get(gzipped_sitemap))
+        .route(
+            "/pages/app.js",
+            get(|| async {
+                (
+                    [(header::CONTENT_TYPE, "application/javascript")],
+                    APP_SCRIPT,
+                )
+            }),
+        )
+        .route("/echo-headers", get(echo_headers))
+        // Nonexistent paths here give pages whose size changes a little
+        // with each request, like a page containing a timestamp
+        .route(
+            "/varying/{*path}",
+            get(move || {
+                let n = varying_requests.fetch_add(1, Ordering::SeqCst) % 4;
+                async move { "x".repeat(1000 + 50 * n) }
+            }),
+        )
         .route("/{*path}", get(get_test_path))
 }
 
+// Returns the user agent and X-Rotate header the request was sent with
+async fn echo_headers(headers: HeaderMap) -> String {
+    let value = |name| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string()
+    };
+    format!("{}|{}", value("user-agent"), value("x-rotate"))
+}
+
 async fn get_test_path(Path(path): Path<String>) -> (StatusCode, String) {
     dbg!(&path);
     let Some(params) = PATHS.get(&path) else {
+        // Nonexistent PHP files are handled differently, as with PHP-FPM
+        if path.ends_with(".php") {
+            return (StatusCode::OK, "No input file specified.".into());
+        }
         return (StatusCode::NOT_FOUND, "Not found".into());
     };
     (params.code, "A".repeat(params.length))
@@ -15,25 +15,46 @@
 // You should have received a copy of the GNU General Public License
 // along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::{arg_parse, request};
+use crate::{
+    arg_parse, content_parse, har, output::DirectoryBaseline, request,
+};
 use curl::easy::Easy2;
+use percent_encoding::percent_decode_str;
 use std::{
-    collections::HashSet,
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
     fmt,
-    sync::{Arc, mpsc},
+    hash::Hash,
+    sync::{Arc, Mutex, mpsc},
+    thread,
+    time::Duration,
 };
 
-use rand::{Rng, distr::Alphanumeric};
+use rand::{Rng, distr::Alphanumeric, seq::IndexedRandom};
 
 use log::{debug, info, warn};
 use url::Url;
 
+// Files which are commonly found in a directory, used to check whether
+// directories with no name to swap the case of are case insensitive
+const COMMON_FILES: [&str; 6] = [
+    "index.html",
+    "index.php",
+    "default.aspx",
+    "default.htm",
+    "iisstart.htm",
+    "robots.txt",
+];
+
 // Struct for passing information back to the main thread
 pub struct DirectoryInfo {
     pub url: Url,
     pub validator: Option<TargetValidator>,
     pub parent_index: usize,
     pub parent_depth: u32,
+    pub case_insensitive: bool,
+    // Validators for paths with each prefix and extension, keyed by the
+    // prefix and extension
+    pub suffix_validators: HashMap<(String, String), TargetValidator>,
 }
 
 impl DirectoryInfo {
@@ -48,6 +69,8 @@ impl DirectoryInfo {
             validator,
             parent_index,
             parent_depth,
+            case_insensitive: false,
+            suffix_validators: HashMap::new(),
         }
     }
 
@@ -58,6 +81,34 @@ impl DirectoryInfo {
             validator: None,
             parent_index: 0,
             parent_depth: 0,
+            case_insensitive: false,
+            suffix_validators: HashMap::new(),
+        }
+    }
+
+    // Describes what nonexistent paths looked like in the directory, for
+    // the reports
+    pub fn baseline(
+        &self,
+        scan_opts: &arg_parse::ScanOpts,
+    ) -> DirectoryBaseline {
+        let validator = self.validator.as_ref();
+        DirectoryBaseline {
+            url: self.url.to_string(),
+            code: validator.map(|validator| validator.response_code),
+            size: validator.and_then(|validator| validator.response_len),
+            size_range: validator
+                .and_then(|validator| validator.response_len_range),
+            diff_size: validator
+                .and_then(|validator| validator.diff_response_len),
+            redirect_url: validator
+                .and_then(|validator| validator.redirect_url.clone()),
+            similarity: validator.and_then(|validator| validator.similarity),
+            alert: validator
+                .and_then(|validator| validator.validator_alert.as_ref())
+                .map(ValidatorAlert::name),
+            skipped: validator
+                .is_some_and(|validator| !validator.scan_folder(scan_opts)),
         }
     }
 }
@@ -69,8 +120,14 @@ pub struct TargetValidator {
     response_code: u32,
     response_len: Option<i32>,
     diff_response_len: Option<i32>,
+    // Inclusive range of sizes, used when the sizes vary slightly
+    response_len_range: Option<(i32, i32)>,
     redirect_url: Option<String>,
     pub validator_alert: Option<ValidatorAlert>,
+    // Hashes of the bodies of the nonexistent paths, used if similarity
+    // detection is enabled
+    content_hashes: Vec<u64>,
+    similarity: Option<u8>,
 }
 
 impl TargetValidator {
@@ -85,11 +142,25 @@ impl TargetValidator {
             response_code,
             response_len,
             diff_response_len,
+            response_len_range: None,
             redirect_url,
             validator_alert,
+            content_hashes: Vec::new(),
+            similarity: None,
         }
     }
 
+    // Sets the bodies that responses are compared against to decide if
+    // they are similar to a not found response
+    pub fn set_content_hashes(
+        &mut self,
+        content_hashes: Vec<u64>,
+        similarity: u8,
+    ) {
+        self.content_hashes = content_hashes;
+        self.similarity = Some(similarity);
+    }
+
     // Function used to compare the validator to a RequestResponse,
     // Returns true if the given request matches the not found
     // definition
@@ -99,26 +170,77 @@ impl TargetValidator {
             return false;
         }
 
-        // If there's a redirect url set then check that
+        // If there's a redirect url set then check that, filling in the
+        // name of the requested path if the redirect included it
         if let Some(redirect_url) = &self.redirect_url {
-            return redirect_url == &response.redirect_url;
+            let redirect_url = redirect_url
+                .replace(REDIRECT_PLACEHOLDER, &requested_name(&response.url));
+            return redirect_url == response.redirect_url;
         }
 
-        // If there is a length in the validator then check against that,
+        // If the content is similar to a not found response then the size
+        // doesn't matter, otherwise it's only not found if the size
+        // matches too
+        if let Some(similarity) = self.similarity {
+            let similar = response.content_hash.is_some_and(|hash| {
+                self.content_hashes.iter().any(|content_hash| {
+                    content_parse::similarity(hash, *content_hash) >= similarity
+                })
+            });
+            return similar || self.matches_size(response).unwrap_or(false);
+        }
+
+        // If there is a size in the validator then check against that,
         // otherwise it is "not found"
-        if let Some(size) = self.response_len {
-            return size == response.content_len as i32;
+        self.matches_size(response).unwrap_or(true)
+    }
+
+    // Returns true if the two validators describe the same not found
+    // response. Sizes which vary between probes give slightly different
+    // ranges, so those only need to overlap.
+    pub fn is_equivalent(&self, other: &TargetValidator) -> bool {
+        if self.response_code != other.response_code
+            || self.redirect_url != other.redirect_url
+        {
+            return false;
         }
 
-        match self.diff_response_len {
-            Some(size) => {
-                let diff = (response.content_len as i32
-                    - response.url.as_str().len() as i32)
-                    .abs();
-                size == diff
+        match (self.size_range(), other.size_range()) {
+            (Some((min, max)), Some((other_min, other_max))) => {
+                min <= other_max && other_min <= max
             }
-            None => true,
+            (None, None) => self.diff_response_len == other.diff_response_len,
+            _ => false,
+        }
+    }
+
+    // The range of sizes a not found response can have, if it has a fixed
+    // size or a range
+    fn size_range(&self) -> Option<(i32, i32)> {
+        self.response_len
+            .map(|size| (size, size))
+            .or(self.response_len_range)
+    }
+
+    // Compares the size of the response against the not found size, if
+    // the validator has one
+    fn matches_size(
+        &self,
+        response: &request::RequestResponse,
+    ) -> Option<bool> {
+        let len = response.content_len as i32;
+
+        if let Some(size) = self.response_len {
+            return Some(size == len);
+        }
+
+        if let Some((min, max)) = self.response_len_range {
+            return Some((min..=max).contains(&len));
         }
+
+        self.diff_response_len.map(|size| {
+            size == (len - response.url.as_str().len() as i32).abs()
+        })
     }
 
     // Return a string summary of this validator's definition
@@ -134,10 +256,18 @@ impl TargetValidator {
             output += &format!("|SIZE:{}", length);
         }
 
+        if let Some((min, max)) = self.response_len_range {
+            output += &format!("|SIZE:{}-{}", min, max);
+        }
+
         if let Some(length) = self.diff_response_len {
             output += &format!("|DIFF_SIZE:{}", length);
         }
 
+        if let Some(similarity) = self.similarity {
+            output += &format!("|SIMILARITY:{}%", similarity);
+        }
+
         output + ")"
     }
 
@@ -149,7 +279,8 @@ impl TargetValidator {
             match validator_alert {
                 ValidatorAlert::Code401 => scan_opts.scan_401,
                 ValidatorAlert::Code403 => scan_opts.scan_403,
-                // Placeholder branch for future use
+                // The redirects are filtered out by the validator so the
+                // directory can still be scanned
                 ValidatorAlert::RedirectToHTTPS => true,
             }
         } else {
@@ -157,6 +288,23 @@ impl TargetValidator {
         }
     }
 
+    // Returns the HTTPS version of the given url if the directory
+    // redirects to HTTPS, using the port from the redirects
+    pub fn https_upgrade(&self, url: &Url) -> Option<Url> {
+        if !matches!(
+            self.validator_alert,
+            Some(ValidatorAlert::RedirectToHTTPS)
+        ) {
+            return None;
+        }
+
+        let redirect_url = Url::parse(self.redirect_url.as_ref()?).ok()?;
+        let mut https_url = url.clone();
+        https_url.set_scheme("https").ok()?;
+        https_url.set_port(redirect_url.port()).ok()?;
+        Some(https_url)
+    }
+
     pub fn print_alert(&self) -> String {
         if let Some(validator_alert) = &self.validator_alert {
             format!(": {}", validator_alert)
@@ -173,7 +321,6 @@ impl TargetValidator {
 pub enum ValidatorAlert {
     Code401,
     Code403,
-    #[allow(dead_code)]
     RedirectToHTTPS,
 }
 
@@ -196,166 +343,601 @@ impl fmt::Display for ValidatorAlert {
                     or provide valid session token or credentials."
                 )
             }
-            // Placeholder branch
             ValidatorAlert::RedirectToHTTPS => {
-                write!(f, "The directory redirected to HTTPS")
+                write!(
+                    f,
+                    "\n    Requests to this directory redirect to HTTPS.\n    \
+                    Use the --follow-https-upgrade flag to scan it over HTTPS."
+                )
             }
         }
     }
 }
 
+impl ValidatorAlert {
+    // Short name of the alert used in reports
+    pub fn name(&self) -> &'static str {
+        match self {
+            ValidatorAlert::Code401 => "CODE_401",
+            ValidatorAlert::Code403 => "CODE_403",
+            ValidatorAlert::RedirectToHTTPS => "REDIRECT_TO_HTTPS",
+        }
+    }
+}
+
 pub fn validator_thread(
     rx: mpsc::Receiver<request::RequestResponse>,
     main_tx: mpsc::Sender<Option<DirectoryInfo>>,
+    har_tx: har::HarSender,
     global_opts: Arc<arg_parse::GlobalOpts>,
 ) {
-    let mut scanned_directories = HashSet::new();
+    // Shared with the workers so that the HTTPS version of a directory is
+    // de-duplicated too
+    let scanned_directories = Arc::new(Mutex::new(HashSet::new()));
+
+    // Directories are validated by a pool of workers. Each is numbered so
+    // that the end of a request thread is only passed on to main once the
+    // directories it found before ending have been validated.
+    let (job_tx, job_rx) = mpsc::channel::<(usize, request::RequestResponse)>();
+    let job_rx = Arc::new(Mutex::new(job_rx));
+    let (done_tx, done_rx) = mpsc::channel();
+    let workers: Vec<_> = (0..global_opts.validator_threads)
+        .map(|_| {
+            let job_rx = job_rx.clone();
+            let done_tx = done_tx.clone();
+            let har_tx = har_tx.clone();
+            let global_opts = global_opts.clone();
+            let scanned_directories = scanned_directories.clone();
+            thread::spawn(move || {
+                validator_worker(
+                    job_rx,
+                    done_tx,
+                    har_tx,
+                    global_opts,
+                    scanned_directories,
+                )
+            })
+        })
+        .collect();
+
+    let mut next_job = 0;
+    let mut in_progress = BTreeSet::new();
+    let mut pending_ends = VecDeque::new();
 
     loop {
+        // Pass on the results of any finished validations
+        while let Ok((job, directory_info)) = done_rx.try_recv() {
+            in_progress.remove(&job);
+            main_tx.send(directory_info).unwrap();
+        }
+
+        // Then any ends which no longer have earlier directories waiting
+        while let Some(&end) = pending_ends.front() {
+            if in_progress.first().is_some_and(|&job| job < end) {
+                break;
+            }
+            pending_ends.pop_front();
+            main_tx.send(Some(DirectoryInfo::generate_end())).unwrap();
+        }
+
         // Get a RequestResponse from the receiver
-        if let Ok(response) = rx.try_recv() {
-            // If the main thread is trying to exit then stop
-            if response.url == Url::parse("data:END").unwrap() {
-                main_tx.send(Some(DirectoryInfo::generate_end())).unwrap();
+        let Ok(response) = rx.recv_timeout(Duration::from_millis(1)) else {
+            continue;
+        };
+
+        // If the main thread is trying to exit then stop
+        if response.url == Url::parse("data:END").unwrap() {
+            pending_ends.push_back(next_job);
+            continue;
+        } else if response.url == Url::parse("data:MAIN ENDING").unwrap() {
+            break;
+        }
+
+        // Don't do anything if it's somehow not a directory
+        // Also don't do anything if it's listable and we aren't
+        // scanning those
+        if !response.is_directory
+            || (response.is_listable && !global_opts.scan_listable)
+        {
+            continue;
+        }
+
+        if !scanned_directories
+            .lock()
+            .unwrap()
+            .insert(response.url.clone())
+        {
+            main_tx.send(None).unwrap();
+            debug!("{} has already been added to the scan queue", response.url);
+            continue;
+        }
+
+        // If there is a max recursion depth set the check that
+        if let Some(max_recursion_depth) = global_opts.max_recursion_depth {
+            // Calculate the depth
+            let depth = response.get_depth();
+
+            // If the depth exceeds the max_recursion_depth
+            // Skip scanning this directory
+            if depth > max_recursion_depth {
                 continue;
-            } else if response.url == Url::parse("data:MAIN ENDING").unwrap() {
-                break;
-            } else {
-                // Don't do anything if it's somehow not a directory
-                // Also don't do anything if it's listable and we aren't
-                // scanning those
-                if !response.is_directory
-                    || (response.is_listable && !global_opts.scan_listable)
-                {
-                    continue;
-                }
-
-                if !scanned_directories.insert(response.url.clone()) {
-                    main_tx.send(None).unwrap();
-                    debug!(
-                        "{} has already been added to the scan queue",
-                        response.url
-                    );
-                    continue;
-                }
-
-                // If there is a max recursion depth set the check that
-                if let Some(max_recursion_depth) =
-                    global_opts.max_recursion_depth
-                {
-                    // Calculate the depth
-                    let depth = response.get_depth();
-
-                    // If the depth exceeds the max_recursion_depth
-                    // Skip scanning this directory
-                    if depth > max_recursion_depth {
-                        continue;
-                    }
-                }
-                //println!("Parent depth: {}, current depth: {}", response.parent_depth, depth);
-
-                // If validation is disabled or if whitelisting is enabled
-                // return a validator of None
-                // The validator is unused if whitelisting is enabled
-                if global_opts.disable_validator || global_opts.whitelist {
-                    let directory_info = DirectoryInfo::new(
-                        response.url,
-                        None,
-                        response.parent_index,
-                        response.parent_depth,
-                    );
-                    main_tx.send(Some(directory_info)).unwrap();
-                    continue;
-                }
-
-                // Generate an easy and make 3 random requests to the
-                // folder
-                let mut easy = request::generate_easy(&global_opts);
-                let responses = make_requests(response.url.clone(), &mut easy);
-
-                //Get a validator
-                let validator_option = determine_not_found(responses);
-
-                // If there is a validator then wrap it in a
-                // DirectoryInfo and send to main
-                if let Some(validator) = validator_option {
-                    info!(
-                        "Detected nonexistent paths for {} are {}",
-                        &response.url,
-                        validator.summary_text()
-                    );
-                    let directory_info = DirectoryInfo::new(
-                        response.url,
-                        Some(validator),
-                        response.parent_index,
-                        response.parent_depth,
-                    );
-                    main_tx.send(Some(directory_info)).unwrap();
-                }
-                // If there isn't a validator then send a none back to
-                // main. This will be ignored but is necessary during
-                // validation of initial directories
-                else {
-                    warn!(
-                        "{} errored too often during validation, skipping scanning",
-                        response.url
-                    );
-                    main_tx.send(None).unwrap();
-                }
             }
         }
+
+        in_progress.insert(next_job);
+        job_tx.send((next_job, response)).unwrap();
+        next_job += 1;
+    }
+
+    // Closing the job channel stops the workers
+    drop(job_tx);
+    for worker in workers {
+        worker.join().unwrap();
+    }
+}
+
+// Validates directories from the job channel until it is closed, sending
+// the results back with the number of the job
+fn validator_worker(
+    job_rx: Arc<Mutex<mpsc::Receiver<(usize, request::RequestResponse)>>>,
+    done_tx: mpsc::Sender<(usize, Option<DirectoryInfo>)>,
+    har_tx: har::HarSender,
+    global_opts: Arc<arg_parse::GlobalOpts>,
+    scanned_directories: Arc<Mutex<HashSet<Url>>>,
+) {
+    loop {
+        let job = job_rx.lock().unwrap().recv();
+        let Ok((job, response)) = job else {
+            break;
+        };
+        let directory_info = validate_directory(
+            response,
+            &global_opts,
+            &har_tx,
+            &scanned_directories,
+        );
+        done_tx.send((job, directory_info)).unwrap();
+    }
+}
+
+// Works out what nonexistent paths look like in the directory. Returns
+// None if it shouldn't be scanned, which main ignores but is necessary
+// during validation of initial directories.
+fn validate_directory(
+    response: request::RequestResponse,
+    global_opts: &Arc<arg_parse::GlobalOpts>,
+    har_tx: &har::HarSender,
+    scanned_directories: &Mutex<HashSet<Url>>,
+) -> Option<DirectoryInfo> {
+    // If validation is disabled or if whitelisting is enabled
+    // return a validator of None
+    // The validator is unused if whitelisting is enabled
+    let mut easy = request::generate_easy(global_opts, har_tx);
+    if global_opts.disable_validator
+        || global_opts.whitelist
+        || rules_replace_validator(global_opts, &response.url)
+    {
+        let mut directory_info = DirectoryInfo::new(
+            response.url,
+            None,
+            response.parent_index,
+            response.parent_depth,
+        );
+        directory_info.case_insensitive = check_case_insensitive(
+            global_opts,
+            &directory_info.url,
+            None,
+            &mut easy,
+        );
+        return Some(directory_info);
+    }
+
+    // Make random requests to the folder and get a
+    // validator from them
+    let mut url = response.url.clone();
+    let mut validator_option =
+        get_validator(url.clone(), "", "", &mut easy, global_opts);
+
+    // If the directory redirects to HTTPS then either scan it
+    // there or let the user know that it can be
+    if let Some(https_url) = validator_option
+        .as_ref()
+        .and_then(|validator| validator.https_upgrade(&url))
+    {
+        if global_opts.follow_https_upgrade {
+            if !scanned_directories
+                .lock()
+                .unwrap()
+                .insert(https_url.clone())
+            {
+                debug!(
+                    "{} has already been added to the scan queue",
+                    https_url
+                );
+                return None;
+            }
+            info!("{} redirects to HTTPS, scanning {}", url, https_url);
+            url = https_url;
+            validator_option =
+                get_validator(url.clone(), "", "", &mut easy, global_opts);
+        } else {
+            warn!(
+                "{}{}",
+                url,
+                validator_option.as_ref().unwrap().print_alert()
+            );
+        }
+    }
+
+    // If there is a validator then wrap it in a
+    // DirectoryInfo and send to main
+    let Some(validator) = validator_option else {
+        warn!(
+            "{} errored too often during validation, skipping scanning",
+            url
+        );
+        return None;
+    };
+
+    info!(
+        "Detected nonexistent paths for {} are {}",
+        &url,
+        validator.summary_text()
+    );
+    let mut directory_info = DirectoryInfo::new(
+        url,
+        Some(validator),
+        response.parent_index,
+        response.parent_depth,
+    );
+    directory_info.case_insensitive = check_case_insensitive(
+        global_opts,
+        &directory_info.url,
+        directory_info.validator.as_ref(),
+        &mut easy,
+    );
+    directory_info.suffix_validators =
+        get_suffix_validators(&directory_info.url, &mut easy, global_opts);
+    Some(directory_info)
+}
+
+// Determines what a not found response looks like in the given folder
+// for paths with the given prefix and extension
+pub fn get_validator(
+    base_url: Url,
+    prefix: &str,
+    extension: &str,
+    easy: &mut Easy2<request::Collector>,
+    global_opts: &arg_parse::GlobalOpts,
+) -> Option<TargetValidator> {
+    let responses = make_requests(
+        base_url,
+        prefix,
+        extension,
+        global_opts.validator_probes,
+        easy,
+    );
+    let content_hashes: Vec<u64> = responses
+        .iter()
+        .filter_map(|response| response.content_hash)
+        .collect();
+
+    // Any 404 is treated as not found regardless of its content
+    let mut validator =
+        determine_not_found(responses, global_opts.size_tolerance)?;
+    if let Some(similarity) = global_opts.similarity
+        && validator.response_code != 404
+        && !content_hashes.is_empty()
+    {
+        validator.set_content_hashes(content_hashes, similarity);
+    }
+    Some(validator)
+}
+
+// Servers often handle paths differently depending on their extension,
+// e.g. passing .php files to PHP-FPM, so each prefix and extension
+// combination gets its own validator
+fn get_suffix_validators(
+    base_url: &Url,
+    easy: &mut Easy2<request::Collector>,
+    global_opts: &arg_parse::GlobalOpts,
+) -> HashMap<(String, String), TargetValidator> {
+    let mut suffix_validators = HashMap::new();
+
+    for prefix in &global_opts.prefixes {
+        for extension in &global_opts.extensions {
+            // The plain paths are covered by the directory's validator
+            if prefix.is_empty() && extension.is_empty() {
+                continue;
+            }
+
+            if let Some(validator) = get_validator(
+                base_url.clone(),
+                prefix,
+                extension,
+                easy,
+                global_opts,
+            ) {
+                debug!(
+                    "Detected nonexistent paths for {}{}*{} are {}",
+                    base_url,
+                    prefix,
+                    extension,
+                    validator.summary_text()
+                );
+                suffix_validators
+                    .insert((prefix.clone(), extension.clone()), validator);
+            }
+        }
+    }
+
+    suffix_validators
+}
+
+// Works out which of the prefixes and extensions being scanned for were
+// used to make a path, so that the validator for them can be used. The
+// longest matching prefix and extension are picked.
+pub fn path_suffix(
+    url: &Url,
+    global_opts: &arg_parse::GlobalOpts,
+) -> (String, String) {
+    // Directories are always checked against the plain validator
+    let filename = url.path().rsplit('/').next().unwrap_or_default();
+
+    let prefix = global_opts
+        .prefixes
+        .iter()
+        .filter(|prefix| filename.starts_with(prefix.as_str()))
+        .max_by_key(|prefix| prefix.len())
+        .cloned()
+        .unwrap_or_default();
+    let extension = global_opts
+        .extensions
+        .iter()
+        .filter(|extension| {
+            filename.len() - prefix.len() > extension.len()
+                && filename.ends_with(extension.as_str())
+        })
+        .max_by_key(|extension| extension.len())
+        .cloned()
+        .unwrap_or_default();
+
+    (prefix, extension)
+}
+
+// Validators for paths which weren't found by scanning their directory,
+// keyed by the directory, prefix and extension so that each is only
+// worked out once
+#[derive(Default)]
+pub struct ValidatorCache {
+    validators: HashMap<(Url, String, String), Option<TargetValidator>>,
+}
+
+impl ValidatorCache {
+    // Records a validator which is already known
+    pub fn insert(
+        &mut self,
+        directory: Url,
+        prefix: String,
+        extension: String,
+        validator: Option<TargetValidator>,
+    ) {
+        self.validators
+            .insert((directory, prefix, extension), validator);
+    }
+
+    // Returns the validator a scan of the path's directory would have
+    // used for it, making requests to the directory if it isn't known
+    pub fn get(
+        &mut self,
+        url: &Url,
+        easy: &mut Easy2<request::Collector>,
+        global_opts: &arg_parse::GlobalOpts,
+    ) -> Option<TargetValidator> {
+        // Validators are only used when a scan would have used them
+        let directory = parent_directory(url);
+        if global_opts.disable_validator
+            || global_opts.whitelist
+            || rules_replace_validator(global_opts, &directory)
+        {
+            return None;
+        }
+
+        let (prefix, extension) = path_suffix(url, global_opts);
+        let mut get_validator = |prefix: &str, extension: &str| {
+            self.validators
+                .entry((
+                    directory.clone(),
+                    prefix.to_string(),
+                    extension.to_string(),
+                ))
+                .or_insert_with(|| {
+                    get_validator(
+                        directory.clone(),
+                        prefix,
+                        extension,
+                        easy,
+                        global_opts,
+                    )
+                })
+                .clone()
+        };
+        // As in a scan, the directory's validator is used if there isn't
+        // one for the prefix and extension
+        get_validator(&prefix, &extension).or_else(|| get_validator("", ""))
+    }
+}
+
+// Returns the directory that the given path is in, which is the parent
+// directory for directories themselves
+pub fn parent_directory(url: &Url) -> Url {
+    if url.path().ends_with('/') {
+        url.join("..").unwrap()
+    } else {
+        url.join(".").unwrap()
     }
 }
 
-// Makes a set of 3 requests to random strings of different lengths in
-// the given folder
+// Makes requests to the given number of random paths in the given folder,
+// with the given prefix and extension
 fn make_requests(
     base_url: Url,
+    prefix: &str,
+    extension: &str,
+    probes: u32,
     easy: &mut Easy2<request::Collector>,
 ) -> Vec<request::RequestResponse> {
     let mut response_vector: Vec<request::RequestResponse> = Vec::new();
 
-    for i in 1..=3 {
-        //let url = format!("{}{}", base_url, rand_string(10 * i));
-        let url = base_url.join(&rand_string(10 * i)).unwrap();
+    for _ in 0..probes {
+        let word = [prefix, &probe_word(), extension].join("");
+        let url = base_url.join(&word).unwrap();
         response_vector.push(request::make_request(easy, url));
     }
 
     response_vector
 }
 
-// Generate a target validator for a given set of 3 responses
+// If case detection is enabled, check whether the given directory is
+// case insensitive and log the result
+fn check_case_insensitive(
+    global_opts: &arg_parse::GlobalOpts,
+    url: &Url,
+    validator: Option<&TargetValidator>,
+    easy: &mut Easy2<request::Collector>,
+) -> bool {
+    if !global_opts.detect_case {
+        return false;
+    }
+
+    let case_insensitive = is_case_insensitive(url, validator, easy);
+    if case_insensitive {
+        info!(
+            "{} is case insensitive, duplicate words will not be requested",
+            url
+        );
+    }
+    case_insensitive
+}
+
+// Requests a path which exists and the same path with the case of its
+// name swapped. If both are found with the same response then the server
+// is treating paths case insensitively. The directory itself is used
+// where it has letters in its name, otherwise files commonly found in
+// directories such as the root of a host are tried.
+fn is_case_insensitive(
+    url: &Url,
+    validator: Option<&TargetValidator>,
+    easy: &mut Easy2<request::Collector>,
+) -> bool {
+    let candidates: Vec<Url> = if swap_case_of_name(url).is_some() {
+        vec![url.clone()]
+    } else {
+        COMMON_FILES
+            .iter()
+            .filter_map(|file| url.join(file).ok())
+            .collect()
+    };
+
+    for candidate in candidates {
+        let Some(swapped_url) = swap_case_of_name(&candidate) else {
+            continue;
+        };
+
+        let original = request::make_request(easy, candidate);
+        if !is_found(&original, validator) {
+            continue;
+        }
+        let swapped = request::make_request(easy, swapped_url);
+
+        return is_found(&swapped, validator)
+            && original.code == swapped.code
+            && original.content_len == swapped.content_len;
+    }
+
+    false
+}
+
+// Whether the response is for a path which exists. A directory which
+// answers every path, such as with a soft 404, can't be checked for case
+// insensitivity as the swapped path would be found either way.
+fn is_found(
+    response: &request::RequestResponse,
+    validator: Option<&TargetValidator>,
+) -> bool {
+    response.code != 0
+        && response.code != 404
+        && !validator.is_some_and(|validator| validator.is_not_found(response))
+}
+
+// Returns the url with the case of the letters in the last path segment
+// swapped, or None if that would not change the url
+fn swap_case_of_name(url: &Url) -> Option<Url> {
+    let mut segments: Vec<String> =
+        url.path_segments()?.map(String::from).collect();
+    let name = segments
+        .iter_mut()
+        .rev()
+        .find(|segment| !segment.is_empty())?;
+
+    let swapped: String = name
+        .chars()
+        .map(|c| {
+            if c.is_lowercase() {
+                c.to_uppercase().next().unwrap_or(c)
+            } else {
+                c.to_lowercase().next().unwrap_or(c)
+            }
+        })
+        .collect();
+    if swapped == *name {
+        return None;
+    }
+    *name = swapped;
+
+    let mut swapped_url = url.clone();
+    swapped_url.set_path(&segments.join("/"));
+    Some(swapped_url)
+}
+
+// Generate a target validator from the responses to the random paths.
+// Each part of the validator is taken from the value which more than half
+// of the responses agree on.
 fn determine_not_found(
     responses: Vec<request::RequestResponse>,
+    size_tolerance: usize,
 ) -> Option<TargetValidator> {
-    if responses.len() < 3 {
+    if responses.len() < 2 {
         return Some(TargetValidator::new(404, None, None, None, None));
     }
 
     let mut validator_alert = None;
 
-    let code = if responses[0].code == responses[1].code
-        || responses[0].code == responses[2].code
-    {
-        responses[0].code
-    } else if responses[1].code == responses[2].code {
-        responses[1].code
-    } else {
-        404
-    };
+    let code =
+        majority(responses.iter().map(|response| response.code)).unwrap_or(404);
+
+    // Only the responses with the not found code describe it
+    let responses: Vec<&request::RequestResponse> = responses
+        .iter()
+        .filter(|response| response.code == code)
+        .collect();
 
     match code {
         0 => {
             return None;
         }
         301 | 302 => {
-            let mut redirect_url = None;
-            if responses[0].redirect_url == responses[1].redirect_url
-                || responses[0].redirect_url == responses[2].redirect_url
-            {
-                redirect_url = Some(responses[0].redirect_url.clone());
-            } else if responses[1].redirect_url == responses[2].redirect_url {
-                redirect_url = Some(responses[1].redirect_url.clone());
+            // Catch-all redirects often include the requested path, e.g.
+            // to /login?next=/path, so that is replaced with a
+            // placeholder before comparing them
+            let redirect_url = majority(
+                responses.iter().map(|response| redirect_template(response)),
+            );
+
+            // If every path is redirected to itself over HTTPS then the
+            // directory should be scanned there instead
+            if responses.iter().all(|response| is_https_upgrade(response)) {
+                validator_alert = Some(ValidatorAlert::RedirectToHTTPS);
             }
 
             return Some(TargetValidator::new(
@@ -363,7 +945,7 @@ fn determine_not_found(
                 None,
                 None,
                 redirect_url,
-                None,
+                validator_alert,
             ));
         }
         401 => {
@@ -378,41 +960,157 @@ fn determine_not_found(
         _ => {}
     }
 
-    let mut response_size = None;
-    if responses[0].content_len == responses[1].content_len
-        || responses[0].content_len == responses[2].content_len
-    {
-        response_size = Some(responses[0].content_len as i32);
-    } else if responses[1].content_len == responses[2].content_len {
-        response_size = Some(responses[1].content_len as i32);
-    }
+    let response_size =
+        majority(responses.iter().map(|response| response.content_len as i32));
 
     let mut diff_response_size = None;
     if response_size.is_none() {
-        let diff_0 = ((responses[0].content_len as i32)
-            - responses[0].url.as_str().len() as i32)
-            .abs();
-        let diff_1 = ((responses[1].content_len as i32)
-            - responses[1].url.as_str().len() as i32)
-            .abs();
-        let diff_2 = ((responses[2].content_len as i32)
-            - responses[2].url.as_str().len() as i32)
-            .abs();
+        diff_response_size = majority(responses.iter().map(|response| {
+            ((response.content_len as i32) - response.url.as_str().len() as i32)
+                .abs()
+        }));
+    }
 
-        if diff_0 == diff_1 || diff_0 == diff_2 {
-            diff_response_size = Some(diff_0);
-        } else if diff_1 == diff_2 {
-            diff_response_size = Some(diff_1);
+    // If the sizes only vary slightly, e.g. because the page includes a
+    // timestamp, then allow any size close to them
+    let mut response_size_range = None;
+    if response_size.is_none() && diff_response_size.is_none() {
+        let sizes = responses.iter().map(|response| response.content_len);
+        let min = sizes.clone().min().unwrap();
+        let max = sizes.max().unwrap();
+        if max - min <= size_tolerance {
+            response_size_range = Some((
+                min.saturating_sub(size_tolerance) as i32,
+                (max + size_tolerance) as i32,
+            ));
         }
     }
 
-    Some(TargetValidator::new(
+    let mut validator = TargetValidator::new(
         code,
         response_size,
         diff_response_size,
         None,
         validator_alert,
-    ))
+    );
+    validator.response_len_range = response_size_range;
+    Some(validator)
+}
+
+// Returns true if the user's not found rules are to be used instead of
+// detecting not found responses in the directory, which is when one of
+// them applies to everything in it
+pub fn rules_replace_validator(
+    global_opts: &arg_parse::GlobalOpts,
+    url: &Url,
+) -> bool {
+    global_opts.not_found_only
+        && global_opts
+            .not_found_rules
+            .iter()
+            .any(|rule| rule_applies(rule, url))
+}
+
+// Returns true if the rule applies to the given url, because it's on the
+// rule's host and under its path
+fn rule_applies(rule: &arg_parse::NotFoundRule, url: &Url) -> bool {
+    rule.host.as_ref().is_none_or(|host| {
+        url.host_str().is_some_and(|h| h.eq_ignore_ascii_case(host))
+    }) && rule
+        .path
+        .as_ref()
+        .is_none_or(|path| url.path().starts_with(path.as_str()))
+}
+
+// Returns true if the response matches any of the user's not found rules
+// which apply to its url
+pub fn matches_not_found_rule(
+    rules: &[arg_parse::NotFoundRule],
+    response: &request::RequestResponse,
+    content: &str,
+) -> bool {
+    rules.iter().any(|rule| {
+        rule_applies(rule, &response.url)
+            && rule.code.is_none_or(|code| code == response.code)
+            && rule.size.is_none_or(|size| size == response.content_len)
+            && rule
+                .regex
+                .as_ref()
+                .is_none_or(|regex| regex.is_match(content))
+    })
+}
+
+// Returns true if the response redirects to the same path over HTTPS
+fn is_https_upgrade(response: &request::RequestResponse) -> bool {
+    let Ok(redirect_url) = Url::parse(&response.redirect_url) else {
+        return false;
+    };
+
+    response.url.scheme() == "http"
+        && redirect_url.scheme() == "https"
+        && redirect_url.host_str() == response.url.host_str()
+        && redirect_url.path() == response.url.path()
+}
+
+// Placeholder for the name of the requested path in redirect urls
+const REDIRECT_PLACEHOLDER: &str = "%WORD%";
+
+// Replaces the name of the requested path in the redirect url of the
+// response with a placeholder
+fn redirect_template(response: &request::RequestResponse) -> String {
+    let name = requested_name(&response.url);
+    if name.is_empty() {
+        return response.redirect_url.clone();
+    }
+    response.redirect_url.replace(&name, REDIRECT_PLACEHOLDER)
+}
+
+// The last segment of the path of the url, decoded to match the decoded
+// redirect urls
+fn requested_name(url: &Url) -> String {
+    let name = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .unwrap_or_default();
+    percent_decode_str(name).decode_utf8_lossy().into_owned()
+}
+
+// Returns the value which more than half of the given values are
+fn majority<T: Eq + Hash>(values: impl Iterator<Item = T>) -> Option<T> {
+    let mut counts = HashMap::new();
+    let mut total = 0;
+    for value in values {
+        *counts.entry(value).or_insert(0) += 1;
+        total += 1;
+    }
+
+    counts
+        .into_iter()
+        .find(|(_, count)| *count * 2 > total)
+        .map(|(value, _)| value)
+}
+
+// Extensions added to some of the random paths
+const PROBE_EXTENSIONS: [&str; 5] = [".html", ".txt", ".bak", ".zip", ".js"];
+
+// Generates a random name for a path which shouldn't exist. The length
+// and characters vary so that the validator isn't tuned to one shape of
+// name.
+fn probe_word() -> String {
+    let mut rng = rand::rng();
+    let length = rng.random_range(8..=32);
+
+    match rng.random_range(0..4) {
+        0 => rand_string(length),
+        1 => (0..length)
+            .map(|_| char::from(rng.random_range(b'a'..=b'z')))
+            .collect(),
+        2 => {
+            let split = rng.random_range(1..length);
+            [rand_string(split), rand_string(length - split)].join(".")
+        }
+        _ => rand_string(length) + PROBE_EXTENSIONS.choose(&mut rng).unwrap(),
+    }
 }
 
 // Based on https://rust-lang-nursery.github.io/rust-cookbook/algorithms/randomness.html
@@ -424,3 +1122,445 @@ fn rand_string(length: usize) -> String {
         .take(length)
         .collect()
 }
+
+#[cfg(test)]
+mod test {
+    use super::{
+        TargetValidator, ValidatorAlert, determine_not_found,
+        get_suffix_validators, get_validator, is_case_insensitive, majority,
+        make_requests, matches_not_found_rule, path_suffix, probe_word,
+        swap_case_of_name, validate_directory, validator_thread,
+    };
+    use crate::{
+        arg_parse::{GlobalOpts, NotFoundRule},
+        content_parse::simhash,
+        request::{fabricate_request_response, generate_easy, make_request},
+    };
+    use regex::Regex;
+    use std::{
+        collections::HashSet,
+        sync::{Arc, Mutex, mpsc},
+        thread,
+    };
+    use url::Url;
+
+    fn probe_response(
+        path: &str,
+        code: u32,
+        content_len: usize,
+    ) -> crate::request::RequestResponse {
+        let mut response = fabricate_request_response(
+            Url::parse("http://example.com/")
+                .unwrap()
+                .join(path)
+                .unwrap(),
+            false,
+            false,
+        );
+        response.code = code;
+        response.content_len = content_len;
+        response
+    }
+
+    #[test]
+    fn test_majority() {
+        assert_eq!(majority([1, 2, 1].into_iter()), Some(1));
+        assert_eq!(majority([1, 2, 1, 2].into_iter()), None);
+        assert_eq!(majority([3, 1, 3, 2, 3].into_iter()), Some(3));
+    }
+
+    #[test]
+    fn test_probe_word() {
+        for _ in 0..100 {
+            let word = probe_word();
+            assert!(word.len() >= 8, "{word} is too short");
+            assert!(
+                word.chars().all(|c| c.is_ascii_alphanumeric() || c == '.'),
+                "{word} contains unexpected characters"
+            );
+        }
+    }
+
+    #[test]
+    fn test_determine_not_found() {
+        // The code most responses agree on is used, with its size
+        let validator = determine_not_found(
+            vec![
+                probe_response("aaaaaaaaaa", 200, 1000),
+                probe_response("bbbbbbbbbbbb", 403, 50),
+                probe_response("cccccccc.txt", 200, 1000),
+                probe_response("dd.dddddddddd", 200, 1000),
+                probe_response("eeeeeeeeeeeeeeeee", 500, 0),
+            ],
+            16,
+        )
+        .unwrap();
+        assert_eq!(validator.summary_text(), "(CODE:200|SIZE:1000)");
+
+        // Sizes which only vary a little give a range
+        let validator = determine_not_found(
+            vec![
+                probe_response("aaaaaaaaaa", 200, 1000),
+                probe_response("bbbbbbbbbbbbbbbb", 200, 1009),
+                probe_response("cccccccccccccccccccccccccc", 200, 1004),
+            ],
+            16,
+        )
+        .unwrap();
+        assert_eq!(validator.summary_text(), "(CODE:200|SIZE:984-1025)");
+        assert!(validator.is_not_found(&probe_response("f", 200, 1020)));
+        assert!(!validator.is_not_found(&probe_response("f", 200, 1030)));
+
+        // Larger differences don't
+        let validator = determine_not_found(
+            vec![
+                probe_response("aaaaaaaaaa", 200, 1000),
+                probe_response("bbbbbbbbbbbbbbbb", 200, 1100),
+                probe_response("cccccccccccccccccccccccccc", 200, 1400),
+            ],
+            16,
+        )
+        .unwrap();
+        assert_eq!(validator.summary_text(), "(CODE:200)");
+
+        // Redirects which include the requested path are templated
+        let redirect = |path: &str| {
+            let mut response = probe_response(path, 302, 0);
+            response.redirect_url =
+                format!("http://example.com/login?next=/{path}");
+            response
+        };
+        let validator = determine_not_found(
+            vec![
+                redirect("aaaaaaaaaa"),
+                redirect("bbbbbbbbbbbbbbbb"),
+                redirect("cc.cccccccccccccccccccccc"),
+            ],
+            16,
+        )
+        .unwrap();
+        assert_eq!(
+            validator.summary_text(),
+            "(CODE:302|DEST:http://example.com/login?next=/%WORD%)"
+        );
+        assert!(validator.is_not_found(&redirect("admin")));
+        let mut elsewhere = redirect("admin");
+        elsewhere.redirect_url = "http://example.com/admin/".into();
+        assert!(!validator.is_not_found(&elsewhere));
+        assert!(validator.validator_alert.is_none());
+
+        // Redirects to the same path over HTTPS raise an alert
+        let upgrade = |path: &str| {
+            let mut response = probe_response(path, 301, 0);
+            response.redirect_url = format!("https://example.com:8443/{path}");
+            response
+        };
+        let validator = determine_not_found(
+            vec![
+                upgrade("aaaaaaaaaa"),
+                upgrade("bbbbbbbbbbbbbbbb"),
+                upgrade("cc.cccccccccccccccccccccc"),
+            ],
+            16,
+        )
+        .unwrap();
+        assert!(matches!(
+            validator.validator_alert,
+            Some(ValidatorAlert::RedirectToHTTPS)
+        ));
+        assert!(validator.is_not_found(&upgrade("admin")));
+        assert_eq!(
+            validator
+                .https_upgrade(&"http://example.com/dir/".parse().unwrap()),
+            Some("https://example.com:8443/dir/".parse().unwrap())
+        );
+
+        // Without a majority the code is assumed to be a 404
+        let validator = determine_not_found(
+            vec![
+                probe_response("aaaaaaaaaa", 200, 1000),
+                probe_response("bbbbbbbbbbbbbbbb", 403, 1000),
+            ],
+            16,
+        )
+        .unwrap();
+        assert_eq!(validator.summary_text(), "(CODE:404)");
+    }
+
+    #[test]
+    fn test_range_survives_reprobe() {
+        let port = crate::test_server::launch();
+        let base =
+            Url::parse(&format!("http://localhost:{port}/varying/")).unwrap();
+        let global_opts = GlobalOpts {
+            size_tolerance: 200,
+            ..Default::default()
+        };
+        let mut easy = generate_easy(&Default::default(), &None);
+
+        let validator =
+            get_validator(base.clone(), "", "", &mut easy, &global_opts)
+                .unwrap();
+        assert_eq!(validator.summary_text(), "(CODE:200|SIZE:800-1300)");
+
+        // Another round of probes gets sizes that weren't seen before,
+        // which are still within the range
+        for response in make_requests(base, "", "", 3, &mut easy) {
+            assert!(validator.is_not_found(&response));
+        }
+    }
+
+    #[test]
+    fn test_path_suffix() {
+        let global_opts = GlobalOpts {
+            prefixes: vec!["".into(), "~".into()],
+            extensions: vec!["".into(), ".php".into(), ".bak.php".into()],
+            ..Default::default()
+        };
+        let suffix = |path: &str| {
+            let url = Url::parse("http://example.com/").unwrap().join(path);
+            path_suffix(&url.unwrap(), &global_opts)
+        };
+
+        assert_eq!(suffix("admin"), ("".into(), "".into()));
+        assert_eq!(suffix("dir/admin.php"), ("".into(), ".php".into()));
+        assert_eq!(suffix("~admin.bak.php"), ("~".into(), ".bak.php".into()));
+        // Directories and bare extensions use the plain validator
+        assert_eq!(suffix("admin.php/"), ("".into(), "".into()));
+        assert_eq!(suffix(".php"), ("".into(), "".into()));
+    }
+
+    #[test]
+    fn test_suffix_validators() {
+        let port = crate::test_server::launch();
+        let url = Url::parse(&format!("http://localhost:{port}/")).unwrap();
+        let global_opts = Arc::new(GlobalOpts {
+            prefixes: vec!["".into()],
+            extensions: vec!["".into(), ".php".into(), ".txt".into()],
+            ..Default::default()
+        });
+        let mut easy = generate_easy(&global_opts, &None);
+
+        let validators = get_suffix_validators(&url, &mut easy, &global_opts);
+        assert_eq!(validators.len(), 2);
+
+        // Nonexistent .php files give a 200 with a fixed body
+        let php = &validators[&("".into(), ".php".into())];
+        assert_eq!(php.response_code, 200);
+        assert_eq!(php.response_len, Some(24));
+
+        let txt = &validators[&("".into(), ".txt".into())];
+        assert_eq!(txt.response_code, 404);
+    }
+
+    #[test]
+    fn test_validation_pool() {
+        let port = crate::test_server::launch();
+        let url = Url::parse(&format!("http://localhost:{port}/")).unwrap();
+        let global_opts = Arc::new(GlobalOpts {
+            validator_threads: 3,
+            ..Default::default()
+        });
+        let (tx, rx) = mpsc::channel();
+        let (main_tx, main_rx) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            validator_thread(rx, main_tx, None, global_opts)
+        });
+
+        for path in ["a/", "b/", "c/", "a/"] {
+            tx.send(fabricate_request_response(
+                url.join(path).unwrap(),
+                true,
+                false,
+            ))
+            .unwrap();
+        }
+        tx.send(fabricate_request_response(
+            Url::parse("data:END").unwrap(),
+            false,
+            false,
+        ))
+        .unwrap();
+
+        // Every directory is answered before the end is passed on, with
+        // the duplicate answered with None
+        let results: Vec<_> = (0..5).map(|_| main_rx.recv().unwrap()).collect();
+        assert_eq!(results[4].as_ref().unwrap().url.as_str(), "data:END");
+        let mut urls: Vec<_> = results[..4]
+            .iter()
+            .flatten()
+            .map(|directory_info| directory_info.url.path().to_string())
+            .collect();
+        urls.sort();
+        assert_eq!(urls, vec!["/a/", "/b/", "/c/"]);
+
+        tx.send(fabricate_request_response(
+            Url::parse("data:MAIN ENDING").unwrap(),
+            false,
+            false,
+        ))
+        .unwrap();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_not_found_rules() {
+        let port = crate::test_server::launch();
+        let url = Url::parse(&format!("http://localhost:{port}/")).unwrap();
+        let rule = |path: Option<&str>| NotFoundRule {
+            host: Some("LOCALHOST".into()),
+            path: path.map(Into::into),
+            code: Some(200),
+            size: None,
+            regex: Some(Regex::new("^No input file").unwrap()),
+        };
+        let global_opts = Arc::new(GlobalOpts {
+            not_found_rules: vec![rule(None)],
+            ..Default::default()
+        });
+        let mut easy = generate_easy(&global_opts, &None);
+
+        let response = make_request(&mut easy, url.join("a.php").unwrap());
+        assert!(response.not_found_rule);
+        let response = make_request(&mut easy, url.join("ok").unwrap());
+        assert!(!response.not_found_rule);
+
+        // Rules only apply under their path
+        let response = fabricate_request_response(
+            url.join("a.php").unwrap(),
+            false,
+            false,
+        );
+        let content = "No input file specified.";
+        assert!(!matches_not_found_rule(
+            &[rule(Some("/api/"))],
+            &response,
+            content
+        ));
+        let mut response = fabricate_request_response(
+            url.join("api/a.php").unwrap(),
+            false,
+            false,
+        );
+        response.code = 200;
+        assert!(matches_not_found_rule(
+            &[rule(Some("/api/"))],
+            &response,
+            content
+        ));
+
+        // The rules can replace automatic detection where they apply
+        let validate = |rule: NotFoundRule| {
+            let global_opts = Arc::new(GlobalOpts {
+                not_found_rules: vec![rule],
+                not_found_only: true,
+                ..Default::default()
+            });
+            validate_directory(
+                fabricate_request_response(url.clone(), true, false),
+                &global_opts,
+                &None,
+                &Mutex::new(HashSet::new()),
+            )
+            .unwrap()
+        };
+        assert!(validate(rule(None)).validator.is_none());
+        assert!(validate(rule(Some("/api/"))).validator.is_some());
+    }
+
+    #[test]
+    fn test_similar_content() {
+        let not_found_page = |token: &str| {
+            format!(
+                "<html><head><title>Page not found</title></head><body>\
+                 <h1>Sorry, that page could not be found</h1><p>Please \
+                 check the address and try again, or return to the home \
+                 page to find what you were looking for.</p><form>\
+                 <input name=\"csrf\" value=\"{token}\"><input \
+                 name=\"q\"><button>Search</button></form><footer>\
+                 Generated at {token} by the example web server</footer>\
+                 </body></html>"
+            )
+        };
+
+        let mut validator = TargetValidator::new(200, None, None, None, None);
+        validator.set_content_hashes(
+            vec![
+                simhash(&not_found_page("a81bd3f2")),
+                simhash(&not_found_page("0c7e11d9a4")),
+            ],
+            90,
+        );
+
+        // A not found page with a different token and size is not found
+        let mut response = fabricate_request_response(
+            Url::parse("http://example.com/missing").unwrap(),
+            false,
+            false,
+        );
+        response.code = 200;
+        response.content_len = 512;
+        response.content_hash = Some(simhash(&not_found_page("5f02e9")));
+        assert!(validator.is_not_found(&response));
+
+        // A different page is found
+        response.content_hash = Some(simhash(
+            "<html><head><title>Admin login</title></head><body><form \
+             action=\"/login\"><input name=\"username\"><input \
+             name=\"password\" type=\"password\"><button>Log in\
+             </button></form></body></html>",
+        ));
+        assert!(!validator.is_not_found(&response));
+    }
+
+    #[test]
+    fn test_swap_case_of_name() {
+        let url = Url::parse("http://example.com/Admin/").unwrap();
+        assert_eq!(
+            swap_case_of_name(&url),
+            Some(Url::parse("http://example.com/aDMIN/").unwrap())
+        );
+
+        // Only the last segment is changed
+        let url = Url::parse("http://example.com/Admin/Files").unwrap();
+        assert_eq!(
+            swap_case_of_name(&url),
+            Some(Url::parse("http://example.com/Admin/fILES").unwrap())
+        );
+
+        // Nothing to swap in the root or a name without letters
+        let url = Url::parse("http://example.com/").unwrap();
+        assert_eq!(swap_case_of_name(&url), None);
+        let url = Url::parse("http://example.com/2019/").unwrap();
+        assert_eq!(swap_case_of_name(&url), None);
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        let port = crate::test_server::launch();
+        let base = Url::parse(&format!("http://localhost:{port}/")).unwrap();
+        let mut easy = generate_easy(&Default::default(), &None);
+
+        // The directory's name is swapped, and the root is checked with a
+        // file in it
+        assert!(is_case_insensitive(
+            &base.join("files/").unwrap(),
+            None,
+            &mut easy
+        ));
+        assert!(is_case_insensitive(&base, None, &mut easy));
+        assert!(!is_case_insensitive(
+            &base.join("pages/docs/").unwrap(),
+            None,
+            &mut easy
+        ));
+
+        // Paths which look like a not found response aren't used
+        let validator = TargetValidator::new(200, Some(5), None, None, None);
+        assert!(!is_case_insensitive(
+            &base.join("files/").unwrap(),
+            Some(&validator),
+            &mut easy
+        ));
+    }
+}
@@ -0,0 +1,470 @@
+// This file is part of Dirble - https://www.github.com/nccgroup/dirble
+// Copyright (C) 2019 Izzy Whistlecroft <Izzy(dot)Whistlecroft(at)nccgroup(dot)com>
+// Released as open source by NCC Group Plc - https://www.nccgroup.com/
+//
+// Dirble is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Dirble is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    arg_parse::GlobalOpts,
+    bypass, har, output_format,
+    request::{self, RequestResponse},
+    request_thread, validator_thread,
+};
+use log::{error, info};
+use select::{document::Document, predicate::Name};
+use serde::Deserialize;
+use std::{
+    fmt,
+    fs::{self, File},
+    io::Write,
+    path::Path,
+    sync::Arc,
+};
+use url::Url;
+
+// How a path from a previous report compares to the current response
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerifyStatus {
+    Present,
+    Gone,
+    StatusChanged,
+    SizeChanged,
+}
+
+impl fmt::Display for VerifyStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyStatus::Present => write!(f, "PRESENT"),
+            VerifyStatus::Gone => write!(f, "GONE"),
+            VerifyStatus::StatusChanged => write!(f, "STATUS CHANGED"),
+            VerifyStatus::SizeChanged => write!(f, "SIZE CHANGED"),
+        }
+    }
+}
+
+pub struct Verification {
+    pub previous: RequestResponse,
+    pub current: RequestResponse,
+    pub status: VerifyStatus,
+}
+
+// An entry of a JSON report, as written by output_format::output_json
+#[derive(Deserialize)]
+struct ReportEntry {
+    url: String,
+    code: u32,
+    size: usize,
+    is_directory: bool,
+    is_listable: bool,
+    redirect_url: String,
+    found_from_listable: bool,
+    #[serde(default)]
+    source: String,
+    #[serde(default)]
+    source_url: String,
+    #[serde(default)]
+    requested_target: Option<String>,
+    #[serde(default)]
+    bypass_technique: Option<String>,
+    #[serde(default)]
+    extra_headers: Vec<String>,
+    #[serde(default)]
+    baseline_drift: bool,
+    #[serde(default)]
+    wildcard_directory: bool,
+    #[serde(default)]
+    listed_size: Option<u64>,
+    #[serde(default)]
+    listed_modified: Option<String>,
+}
+
+// JSON reports have the paths alongside the directory baselines, though
+// older reports are just a list of paths
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonReport {
+    Scan { paths: Vec<ReportEntry> },
+    Paths(Vec<ReportEntry>),
+}
+
+impl TryFrom<ReportEntry> for RequestResponse {
+    type Error = String;
+
+    fn try_from(entry: ReportEntry) -> Result<Self, String> {
+        let url = Url::parse(&entry.url).map_err(|e| e.to_string())?;
+        let mut response = request::fabricate_request_response(
+            url,
+            entry.is_directory,
+            entry.is_listable,
+        );
+        response.code = entry.code;
+        response.content_len = entry.size;
+        response.redirect_url = entry.redirect_url;
+        response.found_from_listable = entry.found_from_listable;
+        response.source = entry.source;
+        response.source_url = entry.source_url;
+        response.requested_target = entry.requested_target;
+        response.bypass_technique = entry.bypass_technique;
+        response.extra_headers = entry.extra_headers;
+        response.baseline_drift = entry.baseline_drift;
+        response.wildcard_directory = entry.wildcard_directory;
+        response.listed_size = entry.listed_size;
+        response.listed_modified = entry.listed_modified;
+        Ok(response)
+    }
+}
+
+// Re-requests everything in the given report and prints how each path
+// has changed since
+pub fn verify_main(
+    global_opts: Arc<GlobalOpts>,
+    report: &Path,
+    har_tx: &har::HarSender,
+) {
+    // Baseline drift and wildcard directory entries mark an event in the
+    // scan rather than a path so there's nothing to verify for them
+    let previous = match load_report(report) {
+        Ok(previous) => previous
+            .into_iter()
+            .filter(|response| {
+                !response.baseline_drift && !response.wildcard_directory
+            })
+            .collect::<Vec<_>>(),
+        Err(e) => {
+            error!("Unable to load report {}: {}", report.display(), e);
+            std::process::exit(1);
+        }
+    };
+    info!(
+        "Verifying {} paths from {}",
+        previous.len(),
+        report.display()
+    );
+
+    let verifications = verify(&global_opts, previous, har_tx);
+
+    let mut lines =
+        vec![format!("Dirble Verification of {}:", report.display())];
+    lines.extend(verifications.iter().map(format_verification));
+
+    for status in [
+        VerifyStatus::Present,
+        VerifyStatus::Gone,
+        VerifyStatus::StatusChanged,
+        VerifyStatus::SizeChanged,
+    ] {
+        let count = verifications
+            .iter()
+            .filter(|verification| verification.status == status)
+            .count();
+        lines.push(format!("{}: {}", status, count));
+    }
+
+    for line in &lines {
+        println!("{}", line);
+    }
+
+    if let Some(output_file) = &global_opts.output_file {
+        let mut handle = match File::create(output_file) {
+            Err(why) => panic!("couldn't create {}: {}", output_file, why),
+            Ok(file) => file,
+        };
+        for line in &lines {
+            writeln!(handle, "{}", line).unwrap();
+        }
+    }
+}
+
+// Makes the requests for each of the previous responses and compares
+// them, using the same not found detection as a scan would
+pub fn verify(
+    global_opts: &Arc<GlobalOpts>,
+    previous: Vec<RequestResponse>,
+    har_tx: &har::HarSender,
+) -> Vec<Verification> {
+    let mut easy = request::generate_easy(global_opts, har_tx);
+    // Paths which were sent without being normalised are sent the same
+    // way again, this makes no difference to the other urls
+    easy.path_as_is(true).unwrap();
+    let mut validators = validator_thread::ValidatorCache::default();
+    let mut verifications = Vec::new();
+
+    for previous in previous {
+        let validator = validators.get(&previous.url, &mut easy, global_opts);
+
+        // Bypasses and raw paths are requested the same way as they were
+        // found, the rest are requested by their url
+        let current = match bypass::bypass_request(&previous) {
+            Some((target, headers)) => request::make_custom_request(
+                &mut easy,
+                &target,
+                &headers,
+                previous.url.clone(),
+            ),
+            None => match &previous.requested_target {
+                Some(target) => request::make_custom_request(
+                    &mut easy,
+                    target,
+                    &[],
+                    previous.url.clone(),
+                ),
+                None => request::make_request(&mut easy, previous.url.clone()),
+            },
+        };
+
+        let status = if !request_thread::should_send_response(
+            global_opts,
+            &current,
+            &validator,
+        ) {
+            VerifyStatus::Gone
+        }
+        // Scraped paths were never requested so have nothing to
+        // compare against
+        else if previous.found_from_listable && previous.code == 0 {
+            VerifyStatus::Present
+        } else if current.code != previous.code {
+            VerifyStatus::StatusChanged
+        } else if current.content_len != previous.content_len {
+            VerifyStatus::SizeChanged
+        } else {
+            VerifyStatus::Present
+        };
+
+        verifications.push(Verification {
+            previous,
+            current,
+            status,
+        });
+
+        // Sleep if throttle is set
+        request::throttle(global_opts);
+    }
+
+    verifications
+}
+
+fn format_verification(verification: &Verification) -> String {
+    let mut line = format!(
+        "{:<14} {}{}",
+        verification.status.to_string(),
+        output_format::output_url(&verification.current),
+        output_format::output_suffix(&verification.current, false)
+    );
+    if verification.status != VerifyStatus::Present {
+        line += &format!(
+            " was {}",
+            output_format::output_suffix(&verification.previous, false)
+        );
+    }
+    line
+}
+
+// Loads the responses from a JSON or XML report, based on its contents
+pub fn load_report(report: &Path) -> Result<Vec<RequestResponse>, String> {
+    let contents = fs::read_to_string(report).map_err(|e| e.to_string())?;
+
+    if contents.trim_start().starts_with('<') {
+        load_xml_report(&contents)
+    } else {
+        let paths = match serde_json::from_str::<JsonReport>(&contents)
+            .map_err(|e| e.to_string())?
+        {
+            JsonReport::Scan { paths } | JsonReport::Paths(paths) => paths,
+        };
+        paths.into_iter().map(TryFrom::try_from).collect()
+    }
+}
+
+fn load_xml_report(contents: &str) -> Result<Vec<RequestResponse>, String> {
+    let document = Document::from(contents);
+
+    document
+        .find(Name("path"))
+        .map(|node| {
+            let attr = |name: &str| {
+                node.attr(name)
+                    .ok_or_else(|| format!("path is missing {}", name))
+            };
+            let parse_attr = |name: &str| {
+                attr(name)?
+                    .parse::<usize>()
+                    .map_err(|e| format!("invalid {}: {}", name, e))
+            };
+
+            let url = Url::parse(attr("url")?).map_err(|e| e.to_string())?;
+            let mut response = request::fabricate_request_response(
+                url,
+                attr("is_directory")? == "true",
+                attr("is_listable")? == "true",
+            );
+            response.code = parse_attr("code")? as u32;
+            response.content_len = parse_attr("content_len")?;
+            response.redirect_url = attr("redirect_url")?.into();
+            response.found_from_listable =
+                attr("found_from_listable")? == "true";
+            response.source = node.attr("source").unwrap_or_default().into();
+            response.source_url =
+                node.attr("source_url").unwrap_or_default().into();
+            response.requested_target =
+                node.attr("requested_target").map(String::from);
+            response.bypass_technique =
+                node.attr("bypass_technique").map(String::from);
+            response.extra_headers = node
+                .children()
+                .filter(|child| child.name() == Some("header"))
+                .map(|header| header.text())
+                .collect();
+            response.baseline_drift =
+                node.attr("baseline_drift") == Some("true");
+            response.wildcard_directory =
+                node.attr("wildcard_directory") == Some("true");
+            response.listed_size = node
+                .attr("listed_size")
+                .map(|size| size.parse())
+                .transpose()
+                .map_err(|e| format!("invalid listed_size: {}", e))?;
+            response.listed_modified =
+                node.attr("listed_modified").map(String::from);
+            Ok(response)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::output_format::{output_json, output_xml};
+    use tempfile::NamedTempFile;
+
+    fn report_entry(url: &str, code: u32, size: usize) -> RequestResponse {
+        let mut response = request::fabricate_request_response(
+            url.parse().unwrap(),
+            false,
+            false,
+        );
+        response.code = code;
+        response.content_len = size;
+        response.found_from_listable = false;
+        response
+    }
+
+    #[test]
+    fn test_load_report() {
+        let mut responses = vec![
+            report_entry("http://example.com/a", 200, 10),
+            report_entry("http://example.com/b?c=\"d\"", 403, 0),
+        ];
+        responses[0].requested_target = Some("http://example.com/./a".into());
+        responses[1].bypass_technique = Some("url rewrite header".into());
+        responses[1].extra_headers = vec!["X-Original-URL: /b?c=<d>&e".into()];
+
+        let mut json_report = NamedTempFile::new().unwrap();
+        let json = responses.iter().map(output_json).collect::<Vec<_>>();
+        write!(
+            json_report,
+            "{{\"paths\":[{}],\n\"directories\":[]}}",
+            json.join(",\n")
+        )
+        .unwrap();
+        assert_eq!(load_report(json_report.path()).unwrap(), responses);
+
+        // Older reports are just a list of paths
+        let mut old_json_report = NamedTempFile::new().unwrap();
+        write!(old_json_report, "[{}]", json.join(",\n")).unwrap();
+        assert_eq!(load_report(old_json_report.path()).unwrap(), responses);
+
+        let mut xml_report = NamedTempFile::new().unwrap();
+        write!(
+            xml_report,
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<dirble_scan>\n{}\
+             </dirble_scan>",
+            responses.iter().map(output_xml).collect::<String>()
+        )
+        .unwrap();
+        assert_eq!(load_report(xml_report.path()).unwrap(), responses);
+    }
+
+    #[test]
+    fn test_verify() {
+        let port = crate::test_server::launch();
+        let base = format!("http://localhost:{port}");
+
+        let previous = vec![
+            report_entry(&format!("{base}/ok"), 200, 10),
+            report_entry(&format!("{base}/gone"), 200, 10),
+            report_entry(&format!("{base}/201"), 200, 11),
+            report_entry(&format!("{base}/"), 200, 20),
+        ];
+
+        let statuses =
+            verify(&Arc::new(GlobalOpts::default()), previous, &None)
+                .into_iter()
+                .map(|verification| verification.status)
+                .collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            vec![
+                VerifyStatus::Present,
+                VerifyStatus::Gone,
+                VerifyStatus::StatusChanged,
+                VerifyStatus::SizeChanged,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_verify_bypass() {
+        let port = crate::test_server::launch();
+        let base = format!("http://localhost:{port}");
+        let global_opts = Arc::new(GlobalOpts::default());
+
+        // The bypass is only found when the header is sent again
+        let target = format!("{base}/echo-headers");
+        let headers = vec!["X-Rotate: bypassed".to_string()];
+        let mut easy = request::generate_easy(&global_opts, &None);
+        let bypassed = request::make_custom_request(
+            &mut easy,
+            &target,
+            &headers,
+            target.parse().unwrap(),
+        );
+
+        let mut previous = report_entry(
+            &format!("{base}/forbidden"),
+            200,
+            bypassed.content_len,
+        );
+        previous.bypass_technique = Some("url rewrite header".into());
+        previous.requested_target = Some(target);
+        previous.extra_headers = headers;
+
+        let verifications = verify(&global_opts, vec![previous], &None);
+        assert_eq!(verifications[0].status, VerifyStatus::Present);
+    }
+
+    #[test]
+    fn test_verify_requested_target() {
+        let port = crate::test_server::launch();
+        let base = format!("http://localhost:{port}");
+
+        // The target that was sent is requested again rather than the url
+        let mut previous = report_entry(&format!("{base}/x"), 200, 10);
+        previous.requested_target = Some(format!("{base}/ok"));
+
+        let verifications =
+            verify(&Arc::new(GlobalOpts::default()), vec![previous], &None);
+        assert_eq!(verifications[0].status, VerifyStatus::Present);
+    }
+}
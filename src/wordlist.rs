@@ -15,18 +15,40 @@
 // You should have received a copy of the GNU General Public License
 // along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::validator_thread::TargetValidator;
+use crate::{arg_parse::PathMode, validator_thread::TargetValidator};
 use chardet::{charset2encoding, detect};
 use encoding::{DecoderTrap, label::encoding_from_whatwg_label};
-use std::{fs, path::Path, sync::Arc};
-use url::Url;
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
+use std::{
+    collections::HashMap,
+    fs,
+    path::Path,
+    sync::{Arc, atomic::AtomicBool},
+};
+use url::{Position, Url};
+
+// Characters which are left alone when encoding words, which are the
+// unreserved characters from RFC 3986
+const WORD_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+// A url generated from the wordlist, along with the exact string to
+// request. These differ when the path is not to be normalised.
+#[derive(Debug, PartialEq)]
+pub struct GeneratedUri {
+    pub target: String,
+    pub url: Url,
+}
 
 // Struct for a UriGenerator, it needs the hostname, the suffix to
 // append, a wordlist and an index into that wordlist
 pub struct UriGenerator {
     pub base: Url,
-    prefix: String,
-    suffix: String,
+    pub prefix: String,
+    pub suffix: String,
     current_index: usize,
     wordlist: Arc<Vec<String>>,
     step_size: usize,
@@ -34,6 +56,15 @@ pub struct UriGenerator {
     pub parent_depth: u32,
     pub validator: Option<TargetValidator>,
     extension_substitution: bool,
+    path_mode: PathMode,
+    add_slash: bool,
+    slash_pending: bool,
+    // Shared by all of the generators for a directory with the same prefix
+    // and extension, and set once it's found to be a wildcard directory for
+    // them so that they all stop
+    pub wildcard: Arc<AtomicBool>,
+    requests: u64,
+    hits: u64,
 }
 
 // Generates a new UriGenerator given various options
@@ -50,6 +81,8 @@ impl UriGenerator {
         parent_depth: u32,
         validator: Option<TargetValidator>,
         extension_substitution: bool,
+        path_mode: PathMode,
+        add_slash: bool,
     ) -> Self {
         Self {
             base,
@@ -62,13 +95,47 @@ impl UriGenerator {
             parent_depth,
             validator,
             extension_substitution,
+            path_mode,
+            add_slash,
+            slash_pending: false,
+            wildcard: Arc::new(AtomicBool::new(false)),
+            requests: 0,
+            hits: 0,
+        }
+    }
+
+    // Records whether a request for a generated url was a hit
+    pub fn record_hit(&mut self, hit: bool) {
+        self.requests += 1;
+        if hit {
+            self.hits += 1;
         }
     }
+
+    pub fn requests(&self) -> u64 {
+        self.requests
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    // The percentage of requests which were hits
+    pub fn hit_percentage(&self) -> u64 {
+        (self.hits * 100).checked_div(self.requests).unwrap_or(0)
+    }
+
+    // Starts counting requests and hits again, so that the next hit
+    // percentage is only for the requests made after this
+    pub fn reset_hits(&mut self) {
+        self.requests = 0;
+        self.hits = 0;
+    }
 }
 
 // Defines iterating over a UriGenerator
 impl Iterator for UriGenerator {
-    type Item = Url;
+    type Item = GeneratedUri;
 
     fn next(&mut self) -> Option<Self::Item> {
         // If we're at the end of the wordlist then return None
@@ -76,31 +143,59 @@ impl Iterator for UriGenerator {
             return None;
         }
 
-        let uri = if !self.extension_substitution {
+        let word = if !self.extension_substitution {
             // Append the prefixed and suffixed filename onto the URI
-            self.base
-                .join(
-                    [
-                        self.prefix.clone(),
-                        self.wordlist[self.current_index].clone(),
-                        self.suffix.clone(),
-                    ]
-                    .join("")
-                    .as_str(),
-                )
-                .unwrap()
+            [
+                self.prefix.clone(),
+                encode_word(&self.wordlist[self.current_index], self.path_mode),
+                self.suffix.clone(),
+            ]
+            .join("")
         } else {
             let word = self.wordlist[self.current_index]
                 .replace("%EXT%", &self.suffix);
-            self.base
-                .join([self.prefix.clone(), word].join("").as_str())
-                .unwrap()
+            [self.prefix.clone(), encode_word(&word, self.path_mode)].join("")
+        };
+
+        // Each word is requested a second time with a trailing slash if
+        // slash variants are enabled
+        let word = if self.slash_pending { word + "/" } else { word };
+        if self.add_slash && !self.slash_pending {
+            self.slash_pending = true;
+        } else {
+            self.slash_pending = false;
+            // Maintain the index into the wordlist
+            self.current_index += self.step_size;
+        }
+
+        let url = self.base.join(&word).unwrap();
+        let target = match self.path_mode {
+            PathMode::Normal => url.to_string(),
+            // Join the word onto the base without normalising it
+            _ => {
+                let base = &self.base[..Position::AfterPath];
+                let directory = &base[..=base.rfind('/').unwrap()];
+                [directory, &word].join("")
+            }
         };
 
-        // Maintain the index into the wordlist
-        self.current_index += self.step_size;
         // Return the generated Uri
-        Some(uri)
+        Some(GeneratedUri { target, url })
+    }
+}
+
+// Percent encodes a word once or twice according to the path mode
+fn encode_word(word: &str, path_mode: PathMode) -> String {
+    match path_mode {
+        PathMode::Normal | PathMode::Raw => word.to_string(),
+        PathMode::Encode => {
+            utf8_percent_encode(word, WORD_ENCODE_SET).to_string()
+        }
+        PathMode::DoubleEncode => {
+            let encoded =
+                utf8_percent_encode(word, WORD_ENCODE_SET).to_string();
+            utf8_percent_encode(&encoded, WORD_ENCODE_SET).to_string()
+        }
     }
 }
 
@@ -134,3 +229,147 @@ pub fn lines_from_file(filename: impl AsRef<Path>) -> Vec<String> {
         }
     }
 }
+
+// Removes words which only differ by case, for use on servers which
+// treat paths case insensitively. The order of the wordlist is kept, and
+// where there is an all lowercase version of a word it is the one that
+// is kept.
+pub fn dedup_case_insensitive(wordlist: &[String]) -> Vec<String> {
+    let mut deduped: Vec<String> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+
+    for word in wordlist {
+        let lowercase = word.to_lowercase();
+        match positions.get(&lowercase) {
+            Some(&position) => {
+                if *word == lowercase {
+                    deduped[position] = word.clone();
+                }
+            }
+            None => {
+                positions.insert(lowercase, deduped.len());
+                deduped.push(word.clone());
+            }
+        }
+    }
+
+    deduped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn generate(
+        words: &[&str],
+        path_mode: PathMode,
+        add_slash: bool,
+    ) -> Vec<GeneratedUri> {
+        UriGenerator::new(
+            Url::parse("http://example.com/dir/").unwrap(),
+            String::new(),
+            ".txt".into(),
+            Arc::new(words.iter().map(|word| word.to_string()).collect()),
+            0,
+            1,
+            0,
+            0,
+            None,
+            false,
+            path_mode,
+            add_slash,
+        )
+        .collect()
+    }
+
+    fn generated(target: &str, url: &str) -> GeneratedUri {
+        GeneratedUri {
+            target: target.into(),
+            url: Url::parse(url).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_uri_generator_path_modes() {
+        assert_eq!(
+            generate(&["a b", "../x"], PathMode::Normal, false),
+            vec![
+                generated(
+                    "http://example.com/dir/a%20b.txt",
+                    "http://example.com/dir/a%20b.txt"
+                ),
+                generated(
+                    "http://example.com/x.txt",
+                    "http://example.com/x.txt"
+                ),
+            ]
+        );
+        assert_eq!(
+            generate(&["../x"], PathMode::Raw, false),
+            vec![generated(
+                "http://example.com/dir/../x.txt",
+                "http://example.com/x.txt"
+            )]
+        );
+        assert_eq!(
+            generate(&["../x"], PathMode::Encode, false),
+            vec![generated(
+                "http://example.com/dir/..%2Fx.txt",
+                "http://example.com/dir/..%2Fx.txt"
+            )]
+        );
+        assert_eq!(
+            generate(&["../x"], PathMode::DoubleEncode, false),
+            vec![generated(
+                "http://example.com/dir/..%252Fx.txt",
+                "http://example.com/dir/..%252Fx.txt"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_uri_generator_slash_variants() {
+        assert_eq!(
+            generate(&["a", "b"], PathMode::Normal, true),
+            vec![
+                generated(
+                    "http://example.com/dir/a.txt",
+                    "http://example.com/dir/a.txt"
+                ),
+                generated(
+                    "http://example.com/dir/a.txt/",
+                    "http://example.com/dir/a.txt/"
+                ),
+                generated(
+                    "http://example.com/dir/b.txt",
+                    "http://example.com/dir/b.txt"
+                ),
+                generated(
+                    "http://example.com/dir/b.txt/",
+                    "http://example.com/dir/b.txt/"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dedup_case_insensitive() {
+        let wordlist: Vec<String> = ["ADMIN", "Admin", "admin", "Login", "x"]
+            .into_iter()
+            .map(Into::into)
+            .collect();
+        let expected: Vec<String> = ["admin", "Login", "x"]
+            .into_iter()
+            .map(Into::into)
+            .collect();
+        assert_eq!(dedup_case_insensitive(&wordlist), expected);
+
+        // The order of the wordlist is kept
+        let wordlist: Vec<String> =
+            ["b", "A", "c", "a"].into_iter().map(Into::into).collect();
+        let expected: Vec<String> =
+            ["b", "a", "c"].into_iter().map(Into::into).collect();
+        assert_eq!(dedup_case_insensitive(&wordlist), expected);
+    }
+}